@@ -0,0 +1,218 @@
+/// How a published file was split into chunks. `Fixed` cuts at a constant
+/// byte offset and is the default; `ContentDefined` uses a rolling-hash
+/// boundary detector so that an insertion near the front of a file only
+/// reshuffles the chunks around the edit, not every chunk after it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChunkingStrategy {
+    #[default]
+    Fixed,
+    ContentDefined {
+        min_size: u64,
+        avg_size: u64,
+        max_size: u64,
+    },
+}
+
+/// FastCDC's defaults, used when a caller opts into content-defined chunking
+/// without tuning the size bounds themselves.
+pub const DEFAULT_MIN_SIZE: u64 = 2 * 1024 * 1024;
+pub const DEFAULT_AVG_SIZE: u64 = 8 * 1024 * 1024;
+pub const DEFAULT_MAX_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Gear-hash table: one pseudo-random 64-bit value per byte value, mixed
+/// into the rolling hash as `hash = (hash << 1).wrapping_add(GEAR[byte])`.
+/// Generated once via a simple 64-bit splitmix so it's reproducible without
+/// vendoring a table; any fixed table works as long as publisher and any
+/// re-chunking caller agree on it, since it only needs to produce
+/// well-distributed, position-independent boundaries.
+fn gear_table() -> [u64; 256] {
+    let mut table = [0u64; 256];
+    let mut seed: u64 = 0x9e3779b97f4a7c15;
+    for slot in table.iter_mut() {
+        seed = seed.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = seed;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        *slot = z ^ (z >> 31);
+    }
+    table
+}
+
+/// Split `data` into content-defined chunk boundaries using a FastCDC-style
+/// Gear-hash rolling window: slide the hash across the bytes and cut
+/// whenever `hash & mask == 0`, never cutting before `min_size`, checking
+/// against an easier (fewer-bit) mask until a chunk reaches `avg_size` and a
+/// stricter (more-bit) mask after it, so cuts come more readily below
+/// `avg_size` than past it, and hard-capping at `max_size` if none ever
+/// appears. Returns `(offset, length)` pairs covering the whole of `data` in
+/// order.
+pub fn content_defined_boundaries(
+    data: &[u8],
+    min_size: u64,
+    avg_size: u64,
+    max_size: u64,
+) -> Vec<(u64, u64)> {
+    let gear = gear_table();
+    // `P(cut) = 1/2^bits`, so a boundary is found on average every
+    // `avg_size` bytes when `bits` is `avg_size`'s power-of-two exponent.
+    let mask_bits = avg_size.max(2).next_power_of_two().trailing_zeros() as u64;
+    let mask_small = (1u64 << mask_bits.saturating_sub(1).max(1)) - 1;
+    let mask_large = (1u64 << (mask_bits + 1).min(63)) - 1;
+
+    let mut boundaries = Vec::new();
+    let mut offset: u64 = 0;
+    let len = data.len() as u64;
+
+    while offset < len {
+        let chunk_start = offset;
+        let mut hash: u64 = 0;
+        let mut pos = chunk_start;
+        let hard_end = u64::min(chunk_start + max_size, len);
+
+        while pos < hard_end {
+            let byte = data[pos as usize];
+            hash = (hash << 1).wrapping_add(gear[byte as usize]);
+            pos += 1;
+
+            let chunk_len = pos - chunk_start;
+            if chunk_len < min_size {
+                continue;
+            }
+            let mask = if chunk_len < avg_size {
+                mask_small
+            } else {
+                mask_large
+            };
+            if hash & mask == 0 {
+                break;
+            }
+        }
+
+        boundaries.push((chunk_start, pos - chunk_start));
+        offset = pos;
+    }
+
+    boundaries
+}
+
+/// Number of chunks a file of `total_bytes` was split into: the length of
+/// `chunk_offsets` when it was chunked by content, or the fixed-size chunk
+/// count derived from `chunk_size` otherwise (a ceiling division, so an
+/// exact multiple of `chunk_size` doesn't produce a phantom trailing index
+/// beyond the real last chunk). Takes the raw fields rather than a
+/// `ChunkFile`/`ChunkFileMeta` directly since both shapes appear across the
+/// crate.
+pub fn chunk_count(total_bytes: u64, chunk_size: u64, chunk_offsets: Option<&[(u64, u64)]>) -> u64 {
+    match chunk_offsets {
+        Some(offsets) => offsets.len() as u64,
+        None => (total_bytes + chunk_size - 1) / chunk_size,
+    }
+}
+
+/// The inclusive `(start, end)` byte window of chunk `index`, for either
+/// chunking mode.
+pub fn chunk_window(
+    total_bytes: u64,
+    chunk_size: u64,
+    chunk_offsets: Option<&[(u64, u64)]>,
+    index: u64,
+) -> (u64, u64) {
+    match chunk_offsets {
+        Some(offsets) => {
+            let (start, len) = offsets[index as usize];
+            (start, start + len - 1)
+        }
+        None => {
+            let start = index * chunk_size;
+            let end = u64::min(start + chunk_size, total_bytes) - 1;
+            (start, end)
+        }
+    }
+}
+
+/// The index of the chunk containing `byte_offset`, for either chunking
+/// mode. Used to translate a requested byte range into the chunk indices
+/// that need to be read/verified to serve it.
+pub fn chunk_index_at(
+    chunk_size: u64,
+    chunk_offsets: Option<&[(u64, u64)]>,
+    byte_offset: u64,
+) -> u64 {
+    match chunk_offsets {
+        Some(offsets) => offsets
+            .partition_point(|&(start, _)| start <= byte_offset)
+            .saturating_sub(1) as u64,
+        None => byte_offset / chunk_size,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pseudo_random_bytes(seed: u64, len: usize) -> Vec<u8> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(6364136223846793005).wrapping_add(1);
+                (state >> 56) as u8
+            })
+            .collect()
+    }
+
+    /// Editing the prefix before a shared tail shouldn't reshuffle every
+    /// boundary in that tail: once far enough past the edit for the rolling
+    /// hash to resync, the same content should cut at the same relative
+    /// offset regardless of what came before it.
+    #[test]
+    fn cdc_boundary_stability_across_prefix_edits() {
+        let shared_tail = pseudo_random_bytes(42, 6000);
+        let prefix_a = pseudo_random_bytes(1, 80);
+        let prefix_b = pseudo_random_bytes(2, 240);
+
+        let mut data_a = prefix_a.clone();
+        data_a.extend_from_slice(&shared_tail);
+        let mut data_b = prefix_b.clone();
+        data_b.extend_from_slice(&shared_tail);
+
+        let (min_size, avg_size, max_size) = (64, 256, 1024);
+        let boundaries_a = content_defined_boundaries(&data_a, min_size, avg_size, max_size);
+        let boundaries_b = content_defined_boundaries(&data_b, min_size, avg_size, max_size);
+
+        let offsets_in_tail_a: std::collections::HashSet<i64> = boundaries_a
+            .iter()
+            .map(|&(start, _)| start as i64 - prefix_a.len() as i64)
+            .filter(|&rel| rel > 128)
+            .collect();
+        let offsets_in_tail_b: std::collections::HashSet<i64> = boundaries_b
+            .iter()
+            .map(|&(start, _)| start as i64 - prefix_b.len() as i64)
+            .filter(|&rel| rel > 128)
+            .collect();
+
+        assert!(
+            offsets_in_tail_a.intersection(&offsets_in_tail_b).count() > 0,
+            "expected at least one shared boundary in the common tail, a={:?} b={:?}",
+            offsets_in_tail_a,
+            offsets_in_tail_b
+        );
+    }
+
+    /// `avg_size = 8MiB` (`2^23`) should yield a mask sized for ~1-in-8MiB
+    /// cut probability (~23 bits), not the previous inverted calculation's
+    /// ~40 bits.
+    #[test]
+    fn mask_bits_target_avg_size_probability() {
+        let mask_bits = DEFAULT_AVG_SIZE.next_power_of_two().trailing_zeros();
+        assert_eq!(mask_bits, 23);
+    }
+
+    #[test]
+    fn chunk_count_handles_exact_multiple_of_chunk_size() {
+        // 10 chunks of 100 bytes each, no phantom 11th index.
+        assert_eq!(chunk_count(1000, 100, None), 10);
+        // A trailing partial chunk still rounds up correctly.
+        assert_eq!(chunk_count(1001, 100, None), 11);
+        assert_eq!(chunk_count(999, 100, None), 10);
+    }
+}
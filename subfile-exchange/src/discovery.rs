@@ -0,0 +1,50 @@
+use async_trait::async_trait;
+
+/// Status: extension point only. This trait and `NoopDiscovery` exist so
+/// `SubfileDownloader` has a swarm fan-out path to call into, but no
+/// DHT-backed implementation ships in this crate yet — `torrent_leecher`'s
+/// `util::librqbit` module only *declares* the relevant submodules
+/// (`dht_utils`, `tracker_comms`, `peer_connection`, `peer_state`,
+/// `torrent_manager`); none of their implementation files are present in
+/// this checkout (`torrent_leecher` has no `Cargo.toml` either), so there is
+/// nothing real to bind against. Actual DHT-based discovery (announce under
+/// a key derived from the manifest hash, query that key, fan client range
+/// requests out across the result) is tracked as separate follow-up work
+/// once `torrent_leecher` has a working DHT/peer stack to depend on; it is
+/// not implemented here and this module should not be read as satisfying
+/// that request on its own.
+///
+/// Finds (and announces to) peers currently serving a subfile, keyed by its
+/// IPFS manifest hash, so `SubfileDownloader` can fan range requests out
+/// across a swarm instead of only the statically configured server list,
+/// once a real backend exists.
+#[async_trait]
+pub trait PeerDiscovery {
+    /// Look up peers currently serving the subfile identified by
+    /// `ipfs_hash`. Each entry is a base URL, in the same shape as a
+    /// statically configured operator endpoint, so it can be probed and
+    /// queried via the existing `file_service` range endpoint unmodified.
+    async fn find_peers(&self, ipfs_hash: &str) -> Result<Vec<String>, anyhow::Error>;
+
+    /// Announce that this node holds `ipfs_hash` and can re-serve it, e.g.
+    /// after finishing a download. A no-op for discovery backends that
+    /// don't serve, or that don't exist yet.
+    async fn announce(&self, _ipfs_hash: &str) -> Result<(), anyhow::Error> {
+        Ok(())
+    }
+}
+
+/// Discovery backend that never finds peers. The only `PeerDiscovery`
+/// implementation this crate ships; used as the default so the downloader's
+/// swarm fan-out path is always present but always falls back to the
+/// configured server list / IPFS gateway, until the follow-up DHT-backed
+/// implementation described above exists to replace it.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoopDiscovery;
+
+#[async_trait]
+impl PeerDiscovery for NoopDiscovery {
+    async fn find_peers(&self, _ipfs_hash: &str) -> Result<Vec<String>, anyhow::Error> {
+        Ok(Vec::new())
+    }
+}
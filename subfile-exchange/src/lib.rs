@@ -1,9 +1,13 @@
+pub mod chunking;
 pub mod config;
+pub mod discovery;
 pub mod errors;
 pub mod file_hasher;
 pub mod file_reader;
 pub mod ipfs;
+pub mod merkle;
 pub mod publisher;
+pub mod sdk;
 pub mod subfile;
 pub mod subfile_client;
 pub mod subfile_finder;
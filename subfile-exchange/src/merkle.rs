@@ -0,0 +1,120 @@
+use sha2::{Digest, Sha256};
+
+/// One step of a Merkle inclusion path: the sibling hash at that level, and
+/// whether the sibling sits on the left (so the caller knows which order to
+/// concatenate in when recomputing the parent hash).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathStep {
+    pub sibling: String,
+    pub sibling_is_left: bool,
+}
+
+/// Hex-encoded SHA-256 of a single chunk's bytes. This is the leaf value
+/// stored in `ChunkFile::chunk_hashes` and the input to `build_merkle_root`.
+pub fn leaf_hash(chunk: &[u8]) -> String {
+    hex::encode(Sha256::digest(chunk))
+}
+
+fn parent_hash(left: &str, right: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(left.as_bytes());
+    hasher.update(right.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+/// Build a binary Merkle tree over `leaves` and return its root, hashing
+/// `SHA256(left || right)` up each level. A level with an odd number of
+/// nodes duplicates its last node so every level pairs off evenly.
+///
+/// Returns an empty string for no leaves, and the leaf itself for a single
+/// leaf, matching the conventional zero/one-element Merkle tree base cases.
+pub fn build_merkle_root(leaves: &[String]) -> String {
+    if leaves.is_empty() {
+        return String::new();
+    }
+
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        if level.len() % 2 == 1 {
+            level.push(level.last().expect("level is non-empty").clone());
+        }
+        level = level
+            .chunks_exact(2)
+            .map(|pair| parent_hash(&pair[0], &pair[1]))
+            .collect();
+    }
+    level.into_iter().next().expect("level is non-empty")
+}
+
+/// Build the inclusion path for `index` within `leaves`, bottom-up: one
+/// `PathStep` per level from the leaf up to (but not including) the root.
+/// A caller holding only the leaf and the root can verify membership by
+/// folding this path with `verify_path`, without needing every other leaf.
+pub fn build_path(leaves: &[String], index: usize) -> Vec<PathStep> {
+    let mut path = Vec::new();
+    let mut level = leaves.to_vec();
+    let mut idx = index;
+
+    while level.len() > 1 {
+        if level.len() % 2 == 1 {
+            level.push(level.last().expect("level is non-empty").clone());
+        }
+
+        let sibling_idx = if idx % 2 == 0 { idx + 1 } else { idx - 1 };
+        path.push(PathStep {
+            sibling: level[sibling_idx].clone(),
+            sibling_is_left: idx % 2 == 1,
+        });
+
+        level = level
+            .chunks_exact(2)
+            .map(|pair| parent_hash(&pair[0], &pair[1]))
+            .collect();
+        idx /= 2;
+    }
+
+    path
+}
+
+/// Fold `leaf` up through `path` and check the result matches `root`. Used
+/// by a downloading client to confirm a chunk it just fetched belongs to the
+/// subfile it trusts, without refetching or rehashing every other chunk.
+pub fn verify_path(leaf: &str, path: &[PathStep], root: &str) -> bool {
+    let computed = path.iter().fold(leaf.to_string(), |acc, step| {
+        if step.sibling_is_left {
+            parent_hash(&step.sibling, &acc)
+        } else {
+            parent_hash(&acc, &step.sibling)
+        }
+    });
+    computed == root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_path_verifies_against_root_for_every_leaf() {
+        let leaves: Vec<String> = (0..7u8).map(|b| leaf_hash(&[b])).collect();
+        let root = build_merkle_root(&leaves);
+
+        for (index, leaf) in leaves.iter().enumerate() {
+            let path = build_path(&leaves, index);
+            assert!(
+                verify_path(leaf, &path, &root),
+                "leaf {} failed to verify against the root",
+                index
+            );
+        }
+    }
+
+    #[test]
+    fn verify_path_rejects_a_tampered_leaf() {
+        let leaves: Vec<String> = (0..4u8).map(|b| leaf_hash(&[b])).collect();
+        let root = build_merkle_root(&leaves);
+        let path = build_path(&leaves, 2);
+
+        assert!(!verify_path(&leaf_hash(b"not the real chunk"), &path, &root));
+    }
+}
@@ -110,10 +110,17 @@ impl SubfilePublisher {
         }
     }
 
+    /// Chunk `file_name` per `self.config.chunking` (fixed-size by default,
+    /// content-defined when configured), hashing each chunk into a leaf and
+    /// folding the leaves into a Merkle root (`ChunkFile::new` does both),
+    /// then serialize the result for publishing to IPFS.
     pub fn write_chunk_file(&self, file_name: &str) -> Result<String, anyhow::Error> {
-        let chunk_file = ChunkFile::new(&self.config.read_dir, file_name, self.config.chunk_size)?;
-        // let merkle_tree = build_merkle_tree(chunks);
-        // let chunk_file = create_chunk_file(&merkle_tree);
+        let chunk_file = ChunkFile::new(
+            &self.config.read_dir,
+            file_name,
+            self.config.chunk_size,
+            self.config.chunking,
+        )?;
 
         tracing::trace!(
             file = tracing::field::debug(&chunk_file),
@@ -0,0 +1,113 @@
+use std::sync::Arc;
+
+use crate::config::DownloaderArgs;
+use crate::discovery::PeerDiscovery;
+use crate::ipfs::IpfsClient;
+use crate::subfile_client::{IndexerEndpoint, SubfileDownloader};
+use crate::subfile_reader::fetch_subfile_from_ipfs;
+
+/// Connection/concurrency options `SubfileClient` applies to every fetch,
+/// as opposed to the per-call `ipfs_hash`/`out_dir`. Mirrors the relevant
+/// subset of `DownloaderArgs`.
+#[derive(Debug, Clone, Default)]
+pub struct SubfileClientConfig {
+    pub indexer_endpoints: Vec<String>,
+    pub gateway_url: Option<String>,
+    pub free_query_auth_token: Option<String>,
+    pub max_retry: u64,
+    pub max_concurrent_streams: u64,
+    pub max_concurrent_files: u64,
+    pub http2_prior_knowledge: bool,
+    pub verify_tls_cert: bool,
+}
+
+/// A manifest's file count and currently known serving capacity, without
+/// downloading anything.
+#[derive(Debug, Clone)]
+pub struct SubfileStatus {
+    pub file_count: usize,
+    pub available_servers: usize,
+}
+
+/// High-level facade over `IpfsClient`, `subfile_reader`, and
+/// `SubfileDownloader` for library users who just want a subfile on disk:
+/// resolve the manifest, discover servers/peers, download every chunk file
+/// with bounded concurrency, verify each chunk as it lands, and reassemble
+/// the output files. Power users who need the low-level range/auth
+/// plumbing can still reach for `SubfileDownloader` directly.
+pub struct SubfileClient {
+    ipfs_client: IpfsClient,
+    config: SubfileClientConfig,
+    discovery: Option<Arc<dyn PeerDiscovery + Send + Sync>>,
+}
+
+impl SubfileClient {
+    pub fn new(ipfs_client: IpfsClient, config: SubfileClientConfig) -> Self {
+        SubfileClient {
+            ipfs_client,
+            config,
+            discovery: None,
+        }
+    }
+
+    /// Use a peer discovery backend (e.g. DHT-backed) instead of
+    /// `SubfileDownloader`'s default `NoopDiscovery` for every fetch this
+    /// client makes.
+    pub fn with_discovery(mut self, discovery: Arc<dyn PeerDiscovery + Send + Sync>) -> Self {
+        self.discovery = Some(discovery);
+        self
+    }
+
+    fn downloader(&self, ipfs_hash: &str, output_dir: &str) -> SubfileDownloader {
+        let args = DownloaderArgs {
+            ipfs_hash: ipfs_hash.to_string(),
+            output_dir: output_dir.to_string(),
+            indexer_endpoints: self.config.indexer_endpoints.clone(),
+            gateway_url: self.config.gateway_url.clone(),
+            free_query_auth_token: self.config.free_query_auth_token.clone(),
+            max_retry: self.config.max_retry,
+            max_concurrent_streams: self.config.max_concurrent_streams,
+            max_concurrent_files: self.config.max_concurrent_files,
+            http2_prior_knowledge: self.config.http2_prior_knowledge,
+            verify_tls_cert: self.config.verify_tls_cert,
+        };
+
+        let mut downloader = SubfileDownloader::new(self.ipfs_client.clone(), args);
+        if let Some(discovery) = &self.discovery {
+            downloader.set_discovery(discovery.clone());
+        }
+        downloader
+    }
+
+    /// Resolve `hash`'s manifest, discover serving peers, download every
+    /// chunk file concurrently (bounded by `max_concurrent_files`),
+    /// verifying each chunk against its hash as it lands, and write the
+    /// reassembled files into `out_dir`.
+    pub async fn fetch_subfile(&self, hash: &str, out_dir: &str) -> Result<(), anyhow::Error> {
+        self.downloader(hash, out_dir).download_subfile().await
+    }
+
+    /// Servers/peers currently known to serve `hash`: the configured
+    /// indexer list plus anything peer discovery turns up, filtered down to
+    /// the ones that actually answered a status probe.
+    pub async fn available_servers(
+        &self,
+        hash: &str,
+    ) -> Result<Vec<IndexerEndpoint>, anyhow::Error> {
+        // `out_dir` is irrelevant for an availability check; no chunk ever
+        // gets written here.
+        self.downloader(hash, ".").check_availability().await
+    }
+
+    /// A snapshot of `hash`'s manifest and current availability, without
+    /// downloading anything.
+    pub async fn status(&self, hash: &str) -> Result<SubfileStatus, anyhow::Error> {
+        let manifest = fetch_subfile_from_ipfs(&self.ipfs_client, hash).await?;
+        let servers = self.available_servers(hash).await?;
+
+        Ok(SubfileStatus {
+            file_count: manifest.files.len(),
+            available_servers: servers.len(),
+        })
+    }
+}
@@ -0,0 +1,110 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::chunking::{self, ChunkingStrategy};
+use crate::merkle;
+
+/// One entry in a `SubfileManifest`: the published name of a file alongside
+/// the IPFS hash of its `ChunkFile` manifest.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileMetaInfo {
+    pub name: String,
+    pub hash: String,
+}
+
+/// Chain block range a subfile's data covers.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockRange {
+    pub start_block: u64,
+    pub end_block: u64,
+}
+
+/// Top-level manifest published to IPFS describing every file in a subfile.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubfileManifest {
+    pub files: Vec<FileMetaInfo>,
+    pub file_type: String,
+    pub spec_version: String,
+    pub description: String,
+    pub chain_id: String,
+    pub block_range: BlockRange,
+}
+
+/// Per-file chunk manifest: how a single published file was split into
+/// chunks, the leaf hash of each chunk, and the Merkle root those leaves
+/// fold up to. `chunk_offsets` is only populated under
+/// `ChunkingStrategy::ContentDefined`; fixed-size chunking derives chunk
+/// windows from `chunk_size` instead (see `crate::chunking::chunk_window`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChunkFile {
+    pub chunk_size: u64,
+    pub total_bytes: u64,
+    pub chunk_hashes: Vec<String>,
+    pub chunk_offsets: Option<Vec<(u64, u64)>>,
+    pub merkle_root: String,
+}
+
+impl ChunkFile {
+    /// Chunk `file_name` under `read_dir` per `chunking` (fixed-size or
+    /// content-defined), hash each chunk into a leaf via `merkle::leaf_hash`,
+    /// and fold the leaves into a Merkle root via `merkle::build_merkle_root`
+    /// so a downloader can verify any one chunk it fetches without
+    /// refetching every other chunk.
+    pub fn new(
+        read_dir: &str,
+        file_name: &str,
+        chunk_size: u64,
+        chunking: ChunkingStrategy,
+    ) -> Result<Self, anyhow::Error> {
+        let mut path = PathBuf::from(read_dir);
+        path.push(file_name);
+        let data = std::fs::read(&path)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+        let total_bytes = data.len() as u64;
+
+        let (chunk_hashes, chunk_offsets): (Vec<String>, Option<Vec<(u64, u64)>>) = match chunking
+        {
+            ChunkingStrategy::Fixed => {
+                let hashes = data
+                    .chunks(chunk_size.max(1) as usize)
+                    .map(merkle::leaf_hash)
+                    .collect();
+                (hashes, None)
+            }
+            ChunkingStrategy::ContentDefined {
+                min_size,
+                avg_size,
+                max_size,
+            } => {
+                let boundaries =
+                    chunking::content_defined_boundaries(&data, min_size, avg_size, max_size);
+                let hashes = boundaries
+                    .iter()
+                    .map(|&(start, len)| {
+                        merkle::leaf_hash(&data[start as usize..(start + len) as usize])
+                    })
+                    .collect();
+                (hashes, Some(boundaries))
+            }
+        };
+
+        let merkle_root = merkle::build_merkle_root(&chunk_hashes);
+
+        Ok(ChunkFile {
+            chunk_size,
+            total_bytes,
+            chunk_hashes,
+            chunk_offsets,
+            merkle_root,
+        })
+    }
+}
+
+/// A file's chunk manifest alongside the `FileMetaInfo` (name/IPFS hash) it
+/// was published under.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChunkFileMeta {
+    pub meta_info: FileMetaInfo,
+    pub chunk_file: ChunkFile,
+}
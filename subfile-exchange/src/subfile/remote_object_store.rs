@@ -1,5 +1,5 @@
-use bytes::Bytes;
-use futures::StreamExt;
+use bytes::{Bytes, BytesMut};
+use futures::{Stream, StreamExt};
 use object_store::{parse_url_opts, path::Path, ObjectStore};
 use reqwest::Url;
 use tokio::io::{AsyncWriteExt};
@@ -14,6 +14,11 @@ use std::sync::Arc;
 
 use crate::subfile::Error;
 
+/// S3 (and compatible) multipart uploads require every part but the last to
+/// be at least 5 MiB, so incoming bytes are buffered up to this size before
+/// a part is actually uploaded.
+const PART_BUFFER_BYTES: usize = 8 * 1024 * 1024;
+
 pub fn s3_store() -> Result<(Box<dyn ObjectStore>, Path), Error> {
     let url = std::env::var("S3_URL").unwrap();
     let url = Url::parse(&url).map_err(|e| Error::InvalidConfig(e.to_string()))?;
@@ -74,6 +79,73 @@ pub async fn write(bytes: Bytes) -> Result<(), Error> {
     Ok(())
 }
 
+/// Stream `bytes` into `path` via a real multipart upload, instead of
+/// collecting the whole subfile in memory for a single `put`. Incoming
+/// bytes are buffered into `PART_BUFFER_BYTES`-sized parts (S3's minimum
+/// part size is 5 MiB, so every part but the last must meet that floor) and
+/// uploaded as each part fills. On any failure the in-progress multipart
+/// upload is aborted so it doesn't linger as a billable, incomplete object;
+/// on success `shutdown()` completes it.
+pub async fn write_subfile(
+    object_store: &dyn ObjectStore,
+    path: &Path,
+    mut stream: impl Stream<Item = Bytes> + Unpin,
+) -> Result<(), Error> {
+    let (multipart_id, mut writer) = object_store
+        .put_multipart(path)
+        .await
+        .map_err(Error::ObjectStoreError)?;
+
+    tracing::debug!(multipart_id, location = %path, "Started multipart upload");
+
+    let mut buffer = BytesMut::new();
+    let mut completed_parts = 0usize;
+
+    let upload = async {
+        while let Some(chunk) = stream.next().await {
+            buffer.extend_from_slice(&chunk);
+            while buffer.len() >= PART_BUFFER_BYTES {
+                let part = buffer.split_to(PART_BUFFER_BYTES).freeze();
+                writer
+                    .write_all(&part)
+                    .await
+                    .map_err(|e| Error::SubfileError(e.to_string()))?;
+                completed_parts += 1;
+                tracing::debug!(multipart_id, completed_parts, "Uploaded multipart part");
+            }
+        }
+        if !buffer.is_empty() {
+            let part = buffer.split().freeze();
+            writer
+                .write_all(&part)
+                .await
+                .map_err(|e| Error::SubfileError(e.to_string()))?;
+            completed_parts += 1;
+        }
+        Ok::<(), Error>(())
+    }
+    .await;
+
+    match upload {
+        Ok(()) => match writer.shutdown().await {
+            Ok(()) => {
+                tracing::info!(multipart_id, completed_parts, "Completed multipart upload");
+                Ok(())
+            }
+            Err(e) => {
+                tracing::error!(multipart_id, err = %e, "Completing multipart upload failed, aborting");
+                let _ = object_store.abort_multipart(path, &multipart_id).await;
+                Err(Error::SubfileError(e.to_string()))
+            }
+        },
+        Err(e) => {
+            tracing::error!(multipart_id, err = %e, "Multipart upload failed, aborting");
+            let _ = object_store.abort_multipart(path, &multipart_id).await;
+            Err(e)
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::{fs::File, io::{BufReader, Read}};
@@ -4,6 +4,7 @@ use serde::de::DeserializeOwned;
 
 use crate::{
     errors::Error,
+    merkle,
     subfile::ipfs::IpfsClient,
     subfile::{ChunkFile, ChunkFileMeta, Subfile, SubfileManifest},
 };
@@ -111,3 +112,18 @@ pub async fn read_subfile(
         chunk_files,
     })
 }
+
+/// Verify a chunk a client just downloaded against `chunk_file`'s trusted
+/// Merkle root, without refetching or rehashing any of its sibling chunks.
+/// `chunk_data` is hashed into a leaf, then folded up through its Merkle
+/// path (recomputed from the already-known `chunk_hashes`) and compared
+/// against the root the manifest was published with.
+pub fn verify_downloaded_chunk(chunk_file: &ChunkFile, index: usize, chunk_data: &[u8]) -> bool {
+    let leaf = merkle::leaf_hash(chunk_data);
+    if chunk_file.chunk_hashes.get(index) != Some(&leaf) {
+        return false;
+    }
+
+    let path = merkle::build_path(&chunk_file.chunk_hashes, index);
+    merkle::verify_path(&leaf, &path, &chunk_file.merkle_root)
+}
@@ -0,0 +1,87 @@
+use std::time::{Duration, Instant};
+
+/// How strongly a new latency sample pulls the EWMA toward itself. Higher
+/// reacts faster to changing conditions, lower smooths out noise.
+const LATENCY_EWMA_ALPHA: f64 = 0.3;
+
+/// Consecutive failures an endpoint can rack up (each past a cooldown)
+/// before it escalates to the permanent `indexer_blocklist` instead of just
+/// cooling down again.
+pub const MAX_CONSECUTIVE_FAILURES: u32 = 5;
+
+/// How long a transiently-failing endpoint is excluded from selection
+/// before it's eligible again, without being permanently blocklisted.
+const COOLDOWN: Duration = Duration::from_secs(30);
+
+/// Rolling per-endpoint health used to weight operator selection. A
+/// transient failure decays the endpoint's score and applies a cooldown;
+/// only repeated failures escalate to the all-or-nothing blocklist.
+#[derive(Debug, Clone)]
+pub struct EndpointStats {
+    success_count: u64,
+    failure_count: u64,
+    consecutive_failures: u32,
+    ewma_latency_ms: f64,
+    cooldown_until: Option<Instant>,
+}
+
+impl Default for EndpointStats {
+    fn default() -> Self {
+        EndpointStats {
+            success_count: 0,
+            failure_count: 0,
+            consecutive_failures: 0,
+            // Start optimistic so a never-tried endpoint isn't starved by
+            // endpoints that have already built up a track record.
+            ewma_latency_ms: 1.0,
+            cooldown_until: None,
+        }
+    }
+}
+
+impl EndpointStats {
+    pub fn record_success(&mut self, latency: Duration) {
+        self.success_count += 1;
+        self.consecutive_failures = 0;
+        self.cooldown_until = None;
+
+        let sample_ms = latency.as_secs_f64() * 1000.0;
+        self.ewma_latency_ms =
+            LATENCY_EWMA_ALPHA * sample_ms + (1.0 - LATENCY_EWMA_ALPHA) * self.ewma_latency_ms;
+    }
+
+    /// Record a transient failure and apply a cooldown. Returns `true` once
+    /// consecutive failures cross `MAX_CONSECUTIVE_FAILURES`, signaling the
+    /// caller to escalate this endpoint to the permanent blocklist.
+    pub fn record_failure(&mut self) -> bool {
+        self.failure_count += 1;
+        self.consecutive_failures += 1;
+        self.cooldown_until = Some(Instant::now() + COOLDOWN);
+        self.consecutive_failures >= MAX_CONSECUTIVE_FAILURES
+    }
+
+    pub fn in_cooldown(&self) -> bool {
+        self.cooldown_until
+            .map(|until| Instant::now() < until)
+            .unwrap_or(false)
+    }
+
+    fn success_rate(&self) -> f64 {
+        let total = self.success_count + self.failure_count;
+        if total == 0 {
+            1.0
+        } else {
+            self.success_count as f64 / total as f64
+        }
+    }
+
+    /// Selection weight: favors endpoints with a high success rate and low
+    /// latency. Endpoints in cooldown score zero so they're never picked
+    /// until they recover.
+    pub fn score(&self) -> f64 {
+        if self.in_cooldown() {
+            return 0.0;
+        }
+        self.success_rate() / self.ewma_latency_ms.max(1.0)
+    }
+}
@@ -1,29 +1,87 @@
 use anyhow::anyhow;
 use bytes::Bytes;
 use futures::{stream, StreamExt};
-use http::header::{AUTHORIZATION, CONTENT_RANGE};
+use http::header::{AUTHORIZATION, CONTENT_RANGE, CONTENT_TYPE, RANGE};
+use object_store::{path::Path as ObjectPath, ObjectStore};
+use rand::distributions::{Distribution, WeightedIndex};
 use rand::seq::SliceRandom;
-use reqwest::Client;
+use reqwest::{Client, Url};
 use std::collections::{HashMap, HashSet};
 use std::fs::File;
-use std::io::{Seek, SeekFrom, Write};
+use std::io::{Read, Seek, SeekFrom, Write};
 use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex as StdMutex};
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::UnboundedSender;
 use tokio::sync::Mutex;
 
+use crate::chunking;
 use crate::config::DownloaderArgs;
 use crate::file_hasher::verify_chunk;
 use crate::ipfs::IpfsClient;
-use crate::subfile::{ChunkFileMeta, FileMetaInfo, SubfileManifest};
+use crate::subfile::{ChunkFile, ChunkFileMeta, FileMetaInfo, SubfileManifest};
+use crate::discovery::{NoopDiscovery, PeerDiscovery};
+use crate::subfile_client::endpoint_stats::{EndpointStats, MAX_CONSECUTIVE_FAILURES};
+use crate::subfile_client::object_store_writer::ChunkAssembler;
 use crate::subfile_reader::{fetch_chunk_file_from_ipfs, fetch_subfile_from_ipfs};
+use crate::subfile_server::range::{self, ByteRange};
 use crate::subfile_server::util::Operator;
 
+pub mod endpoint_stats;
+pub mod object_store_writer;
+
+/// Where downloaded chunk files get written. `output_dir` stays a local
+/// directory by default; when it parses as an object-store URL (`s3://`,
+/// `gs://`, ...) chunks are instead assembled into a multipart upload, since
+/// `ObjectStore::put` has no way to write at an arbitrary byte offset.
+enum OutputDestination {
+    LocalDir(String),
+    Store(Arc<dyn ObjectStore>, ObjectPath),
+}
+
+/// Where a single chunk's verified bytes get written to: either seeked
+/// directly into a local file, or submitted (possibly out of order) to a
+/// multipart upload assembler.
+#[derive(Clone)]
+enum OutputSink {
+    File(Arc<Mutex<File>>),
+    Assembler(Arc<Mutex<ChunkAssembler>>),
+}
+
+fn resolve_output_destination(output_dir: &str) -> OutputDestination {
+    match Url::parse(output_dir) {
+        Ok(url) if url.scheme() != "file" => match object_store::parse_url(&url) {
+            Ok((store, path)) => OutputDestination::Store(Arc::from(store), path),
+            Err(e) => {
+                tracing::warn!(
+                    err = %e,
+                    output_dir,
+                    "Failed to parse output_dir as an object store URL, falling back to local filesystem"
+                );
+                OutputDestination::LocalDir(output_dir.to_string())
+            }
+        },
+        _ => OutputDestination::LocalDir(output_dir.to_string()),
+    }
+}
+
 // Pair indexer operator address and indexer service endpoint
 // persumeably this should not be handled by clients themselves
 //TODO: smarter type for tracking available endpoints
 pub type IndexerEndpoint = (String, String);
 
+/// Emitted on a caller-provided channel as chunk files download, so a CLI
+/// or UI can render throughput instead of waiting silently on
+/// `download_subfile`/`download_chunk_file`.
+#[derive(Debug, Clone)]
+pub struct DownloadProgress {
+    pub chunk_file_hash: String,
+    pub bytes_downloaded: u64,
+    pub total_bytes: u64,
+    pub chunks_remaining: usize,
+}
+
 pub struct SubfileDownloader {
     ipfs_client: IpfsClient,
     http_client: reqwest::Client,
@@ -31,34 +89,99 @@ pub struct SubfileDownloader {
     _gateway_url: Option<String>,
     static_endpoints: Vec<String>,
     output_dir: String,
+    output: OutputDestination,
     free_query_auth_token: Option<String>,
     indexer_blocklist: Arc<StdMutex<HashSet<String>>>,
+    // Rolling success-rate/latency stats per endpoint, used to weight
+    // selection in `download_range_request` away from uniform random.
+    endpoint_stats: Arc<StdMutex<HashMap<String, EndpointStats>>>,
     // key is the chunk file identifier (IPFS hash) and value is a HashSet of downloaded chunk indices
     chunks_to_download: Arc<Mutex<HashMap<String, HashSet<u64>>>>,
     chunk_max_retry: u64,
+    // Bounds how many range requests may be in flight at once across all
+    // endpoints. With HTTP/2 prior-knowledge enabled on `http_client`,
+    // concurrent requests to the same operator are multiplexed as streams
+    // over one connection instead of opening one TCP connection per chunk.
+    in_flight_streams: Arc<tokio::sync::Semaphore>,
+    // How many chunk files `download_subfile` downloads concurrently.
+    max_concurrent_files: usize,
+    progress_tx: Option<UnboundedSender<DownloadProgress>>,
+    // Finds peers currently serving this subfile beyond `static_endpoints`,
+    // so a swarm of nodes that have already downloaded it can re-serve its
+    // chunks. Defaults to `NoopDiscovery`, which always falls back to the
+    // static list / IPFS gateway.
+    discovery: Arc<dyn PeerDiscovery + Send + Sync>,
 }
 
 impl SubfileDownloader {
     pub fn new(ipfs_client: IpfsClient, args: DownloaderArgs) -> Self {
+        let http_client = if args.http2_prior_knowledge {
+            reqwest::Client::builder()
+                .http2_prior_knowledge()
+                .build()
+                .expect("Failed to build HTTP/2 client")
+        } else {
+            reqwest::Client::new()
+        };
+
+        let require_tls = args.verify_tls_cert;
+        // Reject plaintext operators up front rather than querying them and
+        // failing later, so a misconfigured endpoint list doesn't silently
+        // fall back to an unencrypted range request.
+        let static_endpoints: Vec<String> = args
+            .indexer_endpoints
+            .into_iter()
+            .filter(|url| {
+                let is_https = url.starts_with("https://");
+                if require_tls && !is_https {
+                    tracing::warn!(url, "Rejecting plaintext operator endpoint, verify_tls_cert is set");
+                }
+                !require_tls || is_https
+            })
+            .collect();
+
         SubfileDownloader {
             ipfs_client,
             //TODO: consider a more advanced config such as if a proxy should be used for the client
-            http_client: reqwest::Client::new(),
+            http_client,
             ipfs_hash: args.ipfs_hash,
             _gateway_url: args.gateway_url,
             //TODO: Check for healthy indexers in args.indexer_endpoints
-            static_endpoints: args.indexer_endpoints,
+            static_endpoints,
+            output: resolve_output_destination(&args.output_dir),
             output_dir: args.output_dir,
             free_query_auth_token: args.free_query_auth_token,
             indexer_blocklist: Arc::new(StdMutex::new(HashSet::new())),
+            endpoint_stats: Arc::new(StdMutex::new(HashMap::new())),
             chunks_to_download: Arc::new(Mutex::new(HashMap::new())),
             chunk_max_retry: args.max_retry,
+            in_flight_streams: Arc::new(tokio::sync::Semaphore::new(
+                args.max_concurrent_streams as usize,
+            )),
+            max_concurrent_files: args.max_concurrent_files as usize,
+            progress_tx: None,
+            discovery: Arc::new(NoopDiscovery),
         }
     }
 
-    /// Check the availability of a subfile, ideally this should go through a gateway/DHT
-    /// but for now we ping an indexer endpoint directly, which is what a gateway
-    /// would do in behave of the downloader
+    /// Subscribe to per-chunk-file download progress. Replaces any
+    /// previously set channel.
+    pub fn set_progress_channel(&mut self, tx: UnboundedSender<DownloadProgress>) {
+        self.progress_tx = Some(tx);
+    }
+
+    /// Swap in a peer discovery backend (e.g. DHT-backed), replacing the
+    /// default `NoopDiscovery`. `check_availability` queries it alongside
+    /// the static endpoint list on every call.
+    pub fn set_discovery(&mut self, discovery: Arc<dyn PeerDiscovery + Send + Sync>) {
+        self.discovery = discovery;
+    }
+
+    /// Check the availability of a subfile: query peer discovery (DHT) for
+    /// nodes currently serving this manifest hash, add them to the
+    /// statically configured server list, then ping every candidate's
+    /// `/status` endpoint, which is what a gateway would do on behalf of
+    /// the downloader.
     /// Return a list of endpoints where the desired subfile is hosted
     //TODO: update once there's a gateway with indexer selection providing endpoints
     //TODO: Use eventuals for continuous pings
@@ -66,16 +189,36 @@ impl SubfileDownloader {
     pub async fn check_availability(&self) -> Result<Vec<IndexerEndpoint>, anyhow::Error> {
         tracing::debug!(subfile_hash = &self.ipfs_hash, "Checking availability");
 
-        // Avoid blocked endpoints
+        // Falls back to an empty set (and so to the static/gateway-only
+        // path below) whenever discovery has nothing, rather than failing
+        // the whole lookup - the DHT is an addition to the static list, not
+        // a replacement for it.
+        let discovered_peers = match self.discovery.find_peers(&self.ipfs_hash).await {
+            Ok(peers) => peers,
+            Err(e) => {
+                tracing::debug!(err = %e, "Peer discovery lookup failed, using static endpoints only");
+                Vec::new()
+            }
+        };
+
+        // Avoid permanently blocked endpoints as well as ones currently
+        // cooling down from a transient failure.
         let blocklist = self
             .indexer_blocklist
             .lock()
             .map_err(|e| anyhow!("Cannot unwrap indexer_blocklist: {}", e.to_string()))?
             .clone();
+        let stats = self
+            .endpoint_stats
+            .lock()
+            .map_err(|e| anyhow!("Cannot unwrap endpoint_stats: {}", e.to_string()))?
+            .clone();
         let filtered_endpoints = self
             .static_endpoints
             .iter()
+            .chain(discovered_peers.iter())
             .filter(|url| !blocklist.contains(*url))
+            .filter(|url| !stats.get(*url).map(|s| s.in_cooldown()).unwrap_or(false))
             .cloned()
             .collect::<Vec<_>>();
         // Use a stream to process the endpoints in parallel
@@ -104,34 +247,104 @@ impl SubfileDownloader {
         blocklist.insert(endpoint);
     }
 
-    /// Read manifest to prepare chunks download
+    /// Read manifest to prepare chunks download. Indices whose bytes are
+    /// already present and verified on disk (e.g. from an interrupted prior
+    /// run) are left out of the set, so the caller only has to fetch what's
+    /// actually missing.
     pub async fn chunks_to_download(&self) -> Result<SubfileManifest, anyhow::Error> {
         let subfile = fetch_subfile_from_ipfs(&self.ipfs_client, &self.ipfs_hash).await?;
-        for chunk_file in &subfile.files {
+        for chunk_file_info in &subfile.files {
             let mut chunks_to_download = self.chunks_to_download.lock().await;
             let chunks_set = chunks_to_download
-                .entry(chunk_file.hash.clone())
+                .entry(chunk_file_info.hash.clone())
                 .or_insert_with(HashSet::new);
             let chunk_file =
-                fetch_chunk_file_from_ipfs(&self.ipfs_client, &chunk_file.hash).await?;
-            let chunk_size = chunk_file.chunk_size;
-            for i in 0..(chunk_file.total_bytes / chunk_size + 1) {
+                fetch_chunk_file_from_ipfs(&self.ipfs_client, &chunk_file_info.hash).await?;
+            for i in 0..chunking::chunk_count(
+                chunk_file.total_bytes,
+                chunk_file.chunk_size,
+                chunk_file.chunk_offsets.as_deref(),
+            ) {
                 chunks_set.insert(i);
             }
+            drop(chunks_to_download);
+
+            let output_path = Path::new(&self.output_dir).join(&chunk_file_info.name);
+            self.skip_already_verified_chunks(&chunk_file_info.hash, &chunk_file, &output_path)
+                .await;
         }
         Ok(subfile)
     }
 
+    /// Read whatever already exists at `output_path`, re-verify each
+    /// chunk-sized window against `chunk_file.chunk_hashes`, and remove the
+    /// indices that already verify from `chunks_to_download`'s set for this
+    /// chunk file. Missing file, short reads, and hash mismatches are all
+    /// treated the same way: leave that index in the download set.
+    async fn skip_already_verified_chunks(
+        &self,
+        chunk_file_hash: &str,
+        chunk_file: &ChunkFile,
+        output_path: &Path,
+    ) {
+        let mut file = match File::open(output_path) {
+            Ok(file) => file,
+            Err(_) => return,
+        };
+
+        let mut chunks_to_download = self.chunks_to_download.lock().await;
+        let chunks_set = chunks_to_download
+            .entry(chunk_file_hash.to_string())
+            .or_insert_with(HashSet::new);
+
+        for i in 0..chunking::chunk_count(
+            chunk_file.total_bytes,
+            chunk_file.chunk_size,
+            chunk_file.chunk_offsets.as_deref(),
+        ) {
+            let (start, end) = chunking::chunk_window(
+                chunk_file.total_bytes,
+                chunk_file.chunk_size,
+                chunk_file.chunk_offsets.as_deref(),
+                i,
+            );
+            let len = (end - start + 1) as usize;
+
+            let mut buf = vec![0u8; len];
+            if file.seek(SeekFrom::Start(start)).is_err() {
+                continue;
+            }
+            if file.read_exact(&mut buf).is_err() {
+                continue;
+            }
+
+            let chunk_hash = &chunk_file.chunk_hashes[i as usize];
+            if verify_chunk(&Bytes::from(buf), chunk_hash) {
+                tracing::debug!(chunk_file_hash, i, "Chunk already present on disk, skipping");
+                chunks_set.remove(&i);
+            }
+        }
+    }
+
     /// Read subfile manifiest and download the individual chunk files
     //TODO: update once there is payment
     pub async fn download_subfile(&self) -> Result<(), anyhow::Error> {
         // Read subfile from ipfs
         let subfile = fetch_subfile_from_ipfs(&self.ipfs_client, &self.ipfs_hash).await?;
 
-        // Loop through chunk files for downloading
+        // Download chunk files concurrently, each file's own chunks are
+        // already parallel (see download_chunk_file), bounded by
+        // max_concurrent_files so a manifest with many files doesn't try to
+        // open all of their connections/streams at once.
+        let results = stream::iter(&subfile.files)
+            .map(|chunk_file| self.download_chunk_file(chunk_file))
+            .buffer_unordered(self.max_concurrent_files.max(1))
+            .collect::<Vec<Result<ChunkFileMeta, anyhow::Error>>>()
+            .await;
+
         let mut incomplete_files = vec![];
-        for chunk_file in &subfile.files {
-            match self.download_chunk_file(chunk_file).await {
+        for result in results {
+            match result {
                 Ok(r) => tracing::info!(file = tracing::field::debug(&r), "Downloaded chunk file"),
                 Err(e) => incomplete_files.push(e),
             }
@@ -176,50 +389,164 @@ impl SubfileDownloader {
             "Basic matching with query availability"
         );
 
-        // Open the output file
-        let file = File::create(Path::new(
-            &(self.output_dir.clone() + "/" + &chunk_file_info.name),
-        ))
-        .unwrap();
-        let file = Arc::new(Mutex::new(file));
+        let total_chunks = chunking::chunk_count(
+            chunk_file.total_bytes,
+            chunk_file.chunk_size,
+            chunk_file.chunk_offsets.as_deref(),
+        );
+
+        // Seed the full index range for this chunk file before anything
+        // removes from it: `skip_already_verified_chunks` below (and the
+        // background completion tracking further down) only ever
+        // `.remove()`s indices it's confirmed are already downloaded, so
+        // without this the set stays empty and nothing gets fetched.
+        {
+            let mut chunks_to_download = self.chunks_to_download.lock().await;
+            let chunks_set = chunks_to_download
+                .entry(chunk_file_info.hash.clone())
+                .or_insert_with(HashSet::new);
+            for i in 0..total_chunks {
+                chunks_set.insert(i);
+            }
+        }
+
+        // Open (or create) the output sink. For a local directory this is a
+        // plain file opened in place, where a prior interrupted run may
+        // have already written some of these chunks; for an object-store
+        // destination there's no such thing as resuming a part-written
+        // object, so every chunk index is fetched again into one fresh
+        // multipart upload for this chunk file.
+        let sink = match &self.output {
+            OutputDestination::LocalDir(dir) => {
+                let output_path = Path::new(dir).join(&chunk_file_info.name);
+                self.skip_already_verified_chunks(&chunk_file_info.hash, &chunk_file, &output_path)
+                    .await;
+
+                let file = File::options()
+                    .create(true)
+                    .write(true)
+                    .open(&output_path)
+                    .unwrap();
+                OutputSink::File(Arc::new(Mutex::new(file)))
+            }
+            OutputDestination::Store(store, prefix) => {
+                let object_path = prefix.child(chunk_file_info.name.as_str());
+                let assembler = ChunkAssembler::new(store.as_ref(), &object_path, total_chunks)
+                    .await
+                    .map_err(|e| anyhow!("Failed to start multipart upload: {}", e))?;
+                OutputSink::Assembler(Arc::new(Mutex::new(assembler)))
+            }
+        };
+
+        // Only the indices still missing from chunks_to_download (i.e. not
+        // already verified on disk) need a range request.
+        let mut remaining: Vec<u64> = self
+            .chunks_to_download
+            .lock()
+            .await
+            .entry(chunk_file_info.hash.clone())
+            .or_insert_with(HashSet::new)
+            .iter()
+            .copied()
+            .collect();
+        remaining.sort_unstable();
+
+        // Group contiguous indices into runs so they can be fetched with a
+        // single multi-range request (`Range: bytes=a-b,c-d`) instead of one
+        // HTTP round trip per chunk.
+        let mut runs: Vec<Vec<u64>> = Vec::new();
+        for i in remaining {
+            match runs.last_mut() {
+                Some(run) if run.last().map(|&last| last + 1) == Some(i) => run.push(i),
+                _ => runs.push(vec![i]),
+            }
+        }
 
-        // Calculate the ranges and spawn threads to download each chunk
-        let chunk_size = chunk_file.chunk_size;
         let mut handles = Vec::new();
+        let bytes_downloaded = Arc::new(AtomicU64::new(0));
 
-        //TODO: use chunks_to_download indices
-        for i in 0..(chunk_file.total_bytes / chunk_size + 1) {
-            tracing::trace!(i, "Download chunk index");
+        for run in runs {
+            tracing::trace!(run = tracing::field::debug(&run), "Download chunk run");
             let chunk_file_hash = chunk_file_info.hash.to_string();
+            let progress_chunk_file_hash = chunk_file_hash.clone();
             let client = self.http_client.clone();
             let request =
-                match self.download_range_request(&meta, &query_endpoints, i, file.clone()) {
+                match self.download_range_request(&meta, &query_endpoints, &run, sink.clone()) {
                     Ok(r) => r,
                     Err(e) => return Err(anyhow::anyhow!("Cannot make range request: {e}")),
                 };
-            let block_list = self.indexer_blocklist.clone();
+            let run_len: u64 = request.windows.iter().map(|w| w.end - w.start + 1).sum();
+            let run_indices = run.clone();
             let chunks_to_download = self.chunks_to_download.clone();
-            let url = request.query_endpoint.clone();
-            // Spawn a new asynchronous task for each range request
+            let endpoint_stats = self.endpoint_stats.clone();
+            let indexer_blocklist = self.indexer_blocklist.clone();
+            let endpoint = request.endpoint.clone();
+            let in_flight_streams = self.in_flight_streams.clone();
+            let bytes_downloaded = bytes_downloaded.clone();
+            let progress_tx = self.progress_tx.clone();
+            let total_bytes = chunk_file.total_bytes;
+            // Spawn a new asynchronous task per run, bounded by
+            // in_flight_streams so a file with thousands of chunks doesn't
+            // open thousands of concurrent streams at once.
             let handle = tokio::spawn(async move {
+                let _permit = in_flight_streams
+                    .acquire_owned()
+                    .await
+                    .expect("in_flight_streams semaphore closed");
+                let started = Instant::now();
                 match download_chunk_and_write_to_file(&client, request).await {
-                    Ok(r) => {
-                        // Update downloaded status
-                        chunks_to_download
+                    Ok(()) => {
+                        endpoint_stats
                             .lock()
-                            .await
-                            .entry(chunk_file_hash)
-                            .or_insert_with(HashSet::new)
-                            .remove(&i);
-                        Ok(r)
+                            .expect("Failed to lock mutex")
+                            .entry(endpoint)
+                            .or_default()
+                            .record_success(started.elapsed());
+                        // Update downloaded status
+                        let chunks_remaining = {
+                            let mut chunks_to_download = chunks_to_download.lock().await;
+                            let set = chunks_to_download
+                                .entry(chunk_file_hash)
+                                .or_insert_with(HashSet::new);
+                            for i in &run_indices {
+                                set.remove(i);
+                            }
+                            set.len()
+                        };
+
+                        if let Some(tx) = &progress_tx {
+                            let bytes_downloaded =
+                                bytes_downloaded.fetch_add(run_len, Ordering::Relaxed) + run_len;
+                            let _ = tx.send(DownloadProgress {
+                                chunk_file_hash: progress_chunk_file_hash,
+                                bytes_downloaded,
+                                total_bytes,
+                                chunks_remaining,
+                            });
+                        }
+                        Ok(())
                     }
                     Err(e) => {
-                        // If the download fails, add the URL to the indexer_blocklist
-                        //TODO: with Error enum, add blocklist based on the error
-                        block_list
+                        // A transient failure decays the endpoint's score and
+                        // applies a cooldown; only repeated failures
+                        // escalate to the permanent blocklist.
+                        let should_blocklist = endpoint_stats
                             .lock()
-                            .expect("Cannot access blocklist")
-                            .insert(url);
+                            .expect("Failed to lock mutex")
+                            .entry(endpoint.clone())
+                            .or_default()
+                            .record_failure();
+                        if should_blocklist {
+                            tracing::warn!(
+                                endpoint,
+                                MAX_CONSECUTIVE_FAILURES,
+                                "Endpoint exceeded consecutive failure threshold, permanently blocklisting"
+                            );
+                            indexer_blocklist
+                                .lock()
+                                .expect("Cannot access blocklist")
+                                .insert(endpoint);
+                        }
                         Err(e)
                     }
                 }
@@ -231,21 +558,7 @@ impl SubfileDownloader {
         let mut failed = vec![];
         for handle in handles {
             match handle.await? {
-                Ok(file) => {
-                    let metadata = file.lock().await.metadata()?;
-
-                    let modified = if let Ok(time) = metadata.modified() {
-                        format!("Modified: {:#?}", time)
-                    } else {
-                        "Not modified".to_string()
-                    };
-
-                    tracing::debug!(
-                        metadata = tracing::field::debug(metadata),
-                        modification = modified,
-                        "Chunk file information"
-                    );
-                }
+                Ok(()) => {}
                 Err(e) => {
                     tracing::warn!(err = e.to_string(), "Chunk file download incomplete");
                     failed.push(e.to_string());
@@ -258,6 +571,16 @@ impl SubfileDownloader {
             return Err(anyhow!("Failed chunks: {:#?}", failed));
         }
 
+        if let OutputSink::Assembler(assembler) = sink {
+            let assembler = Arc::try_unwrap(assembler)
+                .map_err(|_| anyhow!("Multipart assembler still has outstanding references"))?
+                .into_inner();
+            assembler
+                .shutdown()
+                .await
+                .map_err(|e| anyhow!("Failed to finalize multipart upload: {}", e))?;
+        }
+
         Ok(meta)
     }
 
@@ -326,94 +649,184 @@ impl SubfileDownloader {
         }
     }
 
-    /// Generate a request to download a chunk
+    /// Pick an endpoint with probability proportional to its score
+    /// (`success_rate / ewma_latency`), so the downloader naturally
+    /// concentrates load on the fastest healthy operators. Endpoints never
+    /// queried before default to an optimistic score, so they still get a
+    /// fair shot without requiring a warm-up pass. Falls back to uniform
+    /// random if every candidate currently scores zero (e.g. right after a
+    /// cooldown wave).
+    fn select_endpoint(&self, query_endpoints: &[(String, String)]) -> Option<(String, String)> {
+        if query_endpoints.is_empty() {
+            return None;
+        }
+
+        let stats = self.endpoint_stats.lock().expect("Failed to lock mutex");
+        let weights: Vec<f64> = query_endpoints
+            .iter()
+            .map(|(_, url)| {
+                stats
+                    .get(url)
+                    .map(EndpointStats::score)
+                    .unwrap_or(1.0)
+                    .max(0.0)
+            })
+            .collect();
+        drop(stats);
+
+        let mut rng = rand::thread_rng();
+        if weights.iter().all(|w| *w <= 0.0) {
+            return query_endpoints.choose(&mut rng).cloned();
+        }
+
+        let dist = WeightedIndex::new(&weights).ok()?;
+        Some(query_endpoints[dist.sample(&mut rng)].clone())
+    }
+
+    /// Generate a request to download one or more (contiguous) chunk
+    /// indices from a single operator in one HTTP round trip.
     fn download_range_request(
         &self,
         meta: &ChunkFileMeta,
         query_endpoints: &Vec<(String, String)>,
-        i: u64,
-        file: Arc<Mutex<File>>,
+        indices: &[u64],
+        sink: OutputSink,
     ) -> Result<DownloadRangeRequest, anyhow::Error> {
-        let mut rng = rand::thread_rng();
-        let url = if let Some((operator, url)) = query_endpoints.choose(&mut rng).cloned() {
-            tracing::debug!(
-                operator,
-                url,
-                chunk = i,
-                chunk_file = meta.meta_info.hash,
-                "Querying operator"
-            );
-            url
-        } else {
-            let err_msg = "Could not choose an operator to query, data unavailable";
-            tracing::warn!(err_msg);
-            return Err(anyhow!(err_msg));
+        let (operator, url) = match self.select_endpoint(query_endpoints) {
+            Some(endpoint) => endpoint,
+            None => {
+                let err_msg = "Could not choose an operator to query, data unavailable";
+                tracing::warn!(err_msg);
+                return Err(anyhow!(err_msg));
+            }
         };
+        tracing::debug!(
+            operator,
+            url,
+            chunks = tracing::field::debug(indices),
+            chunk_file = meta.meta_info.hash,
+            "Querying operator"
+        );
         //TODO: do no add ipfs_hash here, let query_endpoint be for later
         //TODO: replace file_name header with file_hash for the file level IPFS
-        let query_endpoint = url + "/subfiles/id/" + &self.ipfs_hash;
+        let query_endpoint = url.clone() + "/subfiles/id/" + &self.ipfs_hash;
         let file_hash = meta.meta_info.hash.clone();
-        let start = i * meta.chunk_file.chunk_size;
-        let end = u64::min(
-            start + meta.chunk_file.chunk_size,
-            meta.chunk_file.total_bytes,
-        ) - 1;
-        let chunk_hash = meta.chunk_file.chunk_hashes[i as usize].clone();
+        let windows = indices
+            .iter()
+            .map(|&i| {
+                let (start, end) = chunking::chunk_window(
+                    meta.chunk_file.total_bytes,
+                    meta.chunk_file.chunk_size,
+                    meta.chunk_file.chunk_offsets.as_deref(),
+                    i,
+                );
+                ChunkWindow {
+                    index: i,
+                    start,
+                    end,
+                    chunk_hash: meta.chunk_file.chunk_hashes[i as usize].clone(),
+                }
+            })
+            .collect();
         Ok(DownloadRangeRequest {
+            endpoint: url,
             query_endpoint,
             file_hash,
-            start,
-            end,
-            chunk_hash,
-            file,
+            windows,
+            sink,
             max_retry: self.chunk_max_retry,
             auth_token: self.free_query_auth_token.clone(),
         })
     }
 }
 
+/// A single chunk's byte window within its chunk file.
+struct ChunkWindow {
+    index: u64,
+    start: u64,
+    end: u64,
+    chunk_hash: String,
+}
+
 pub struct DownloadRangeRequest {
+    // Bare operator URL (no path suffix), used to key indexer_blocklist and
+    // endpoint_stats consistently with how static_endpoints is stored.
+    endpoint: String,
     query_endpoint: String,
     auth_token: Option<String>,
     file_hash: String,
-    start: u64,
-    end: u64,
-    chunk_hash: String,
-    file: Arc<Mutex<File>>,
+    windows: Vec<ChunkWindow>,
+    sink: OutputSink,
     max_retry: u64,
 }
 
-/// Make request to download a chunk and write it to the file in position
+/// Make one request covering every window in `request.windows` — a single
+/// `Range: bytes=a-b` request for one window, or a combined
+/// `Range: bytes=a-b,c-d,...` request split back apart from the server's
+/// `multipart/byteranges` response when there's more than one — and write
+/// each verified chunk to its output sink (at its own `start` for a local
+/// file, or at its own `index` for a multipart assembler). Retries the
+/// whole group together on failure or a verification mismatch.
 async fn download_chunk_and_write_to_file(
     http_client: &Client,
     request: DownloadRangeRequest,
-) -> Result<Arc<Mutex<File>>, anyhow::Error> {
+) -> Result<(), anyhow::Error> {
     let mut attempts = 0;
 
+    let ranges: Vec<ByteRange> = request.windows.iter().map(|w| (w.start, w.end)).collect();
+
     loop {
-        // Make the range request to download the chunk
-        match request_chunk(
+        // Make the range request for every window in this group
+        match request_chunk_windows(
             http_client,
             &request.query_endpoint,
             request.auth_token.clone(),
             &request.file_hash,
-            request.start,
-            request.end,
+            &ranges,
         )
         .await
         {
-            Ok(data) => {
-                if verify_chunk(&data, &request.chunk_hash) {
-                    // Lock the file for writing
-                    let mut file_lock = request.file.lock().await;
-                    file_lock.seek(SeekFrom::Start(request.start))?;
-                    file_lock.write_all(&data)?;
-                    drop(file_lock);
-                    return Ok(request.file); // Successfully written the chunk, exit loop
+            Ok(parts) if parts.len() == request.windows.len() => {
+                let all_verified = request
+                    .windows
+                    .iter()
+                    .zip(parts.iter())
+                    .all(|(window, data)| verify_chunk(data, &window.chunk_hash));
+
+                if all_verified {
+                    for (window, data) in request.windows.iter().zip(parts.into_iter()) {
+                        match &request.sink {
+                            OutputSink::File(file) => {
+                                let mut file_lock = file.lock().await;
+                                file_lock.seek(SeekFrom::Start(window.start))?;
+                                file_lock.write_all(&data)?;
+                            }
+                            OutputSink::Assembler(assembler) => {
+                                assembler
+                                    .lock()
+                                    .await
+                                    .submit(window.index, data)
+                                    .await
+                                    .map_err(|e| {
+                                        anyhow!(
+                                            "Failed to submit chunk to multipart upload: {}",
+                                            e
+                                        )
+                                    })?;
+                            }
+                        }
+                    }
+                    return Ok(()); // Successfully written every chunk in the group, exit loop
                 } else {
                     tracing::warn!(request.query_endpoint, "Failed to validate received chunk");
                 }
             }
+            Ok(parts) => tracing::warn!(
+                request.query_endpoint,
+                expected = request.windows.len(),
+                got = parts.len(),
+                "Server returned an unexpected number of byte ranges"
+            ),
             Err(e) => tracing::error!("Chunk download error: {:?}", e),
         }
 
@@ -425,18 +838,22 @@ async fn download_chunk_and_write_to_file(
         tokio::time::sleep(Duration::from_secs(1)).await;
     }
 }
-/// Make range request for a file to the subfile server
-async fn request_chunk(
+
+/// Make a range request for one or more byte windows of a file to the
+/// subfile server, returning each window's bytes in the order requested.
+/// Sends a standard `Range: bytes=a-b` header for a single window (or
+/// `bytes=a-b,c-d,...` for several), and confirms the server actually
+/// honored it rather than silently falling back to the whole object: a
+/// single window must come back as a `206` with a matching `Content-Range`,
+/// and several windows must come back as `multipart/byteranges`.
+async fn request_chunk_windows(
     http_client: &Client,
     query_endpoint: &str,
     auth_token: Option<String>,
     file_hash: &str,
-    start: u64,
-    end: u64,
-) -> Result<Bytes, anyhow::Error> {
-    // For example, to request the first 1024 bytes
-    // The client should be smart enough to take care of proper chunking through subfile metadata
-    let range = format!("bytes={}-{}", start, end);
+    ranges: &[ByteRange],
+) -> Result<Vec<Bytes>, anyhow::Error> {
+    let range_header = range::format_ranges_header(ranges);
     //TODO: implement payment flow
     // if auth_token.is_none() {
     //     tracing::error!(
@@ -445,11 +862,11 @@ async fn request_chunk(
     //     Err(anyhow!("No auth token"))
     // };
 
-    tracing::debug!(query_endpoint, range, "Make range request");
+    tracing::debug!(query_endpoint, range_header, "Make range request");
     let response = http_client
         .get(query_endpoint)
         .header("file_hash", file_hash)
-        .header(CONTENT_RANGE, range)
+        .header(RANGE, range_header)
         .header(
             AUTHORIZATION,
             auth_token.expect("No payment nor auth token"),
@@ -457,10 +874,7 @@ async fn request_chunk(
         .send()
         .await?;
 
-    // Check if the server supports range requests
-    if response.status().is_success() && response.headers().contains_key(CONTENT_RANGE) {
-        Ok(response.bytes().await?)
-    } else {
+    if !response.status().is_success() {
         let err_msg = format!(
             "Server does not support range requests or the request failed: {:#?}",
             tracing::field::debug(&response.status()),
@@ -468,9 +882,38 @@ async fn request_chunk(
         tracing::error!(
             status = tracing::field::debug(&response.status()),
             headers = tracing::field::debug(&response.headers()),
-            chunk = tracing::field::debug(&response),
             "Server does not support range requests or the request failed"
         );
-        Err(anyhow!("Range request failed: {}", err_msg))
+        return Err(anyhow!("Range request failed: {}", err_msg));
+    }
+
+    let is_multipart = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.starts_with("multipart/byteranges"))
+        .unwrap_or(false);
+
+    if ranges.len() > 1 || is_multipart {
+        let body = response.bytes().await?;
+        let parts = range::parse_multipart_byteranges(&body)?;
+        if parts.len() != ranges.len() || parts.iter().map(|(r, _)| r).ne(ranges.iter()) {
+            return Err(anyhow!(
+                "Server's multipart/byteranges response doesn't match the requested ranges"
+            ));
+        }
+        Ok(parts.into_iter().map(|(_, data)| data).collect())
+    } else {
+        if response.status() != reqwest::StatusCode::PARTIAL_CONTENT {
+            return Err(anyhow!(
+                "Server answered a single-range request with {} instead of 206 Partial Content",
+                response.status()
+            ));
+        }
+        let content_range = response.headers().get(CONTENT_RANGE).cloned().ok_or_else(|| {
+            anyhow!("Server did not return a Content-Range header for a single-range request")
+        })?;
+        range::validate_content_range(&content_range, ranges[0])?;
+        Ok(vec![response.bytes().await?])
     }
 }
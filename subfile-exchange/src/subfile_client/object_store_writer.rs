@@ -0,0 +1,97 @@
+use std::collections::BTreeMap;
+
+use bytes::{Bytes, BytesMut};
+use object_store::{path::Path as ObjectPath, MultipartId, ObjectStore};
+use tokio::io::AsyncWriteExt;
+
+use crate::subfile::Error;
+
+/// S3 (and compatible) multipart uploads require every part but the last to
+/// be at least 5 MiB, so we coalesce verified chunks up to this size before
+/// flushing a part; `chunk_size` itself can stay far smaller without
+/// turning into one HTTP request per chunk.
+const PART_BUFFER_BYTES: usize = 8 * 1024 * 1024;
+
+/// Assembles hash-verified chunks for a single output file into a
+/// multipart upload, writing parts to the store in index order as
+/// contiguous runs of chunks become available. Chunks can arrive out of
+/// order (the downloader fires one request per run of contiguous missing
+/// indices concurrently),
+/// so chunks ahead of `next_index` are buffered until the gap behind them
+/// fills in.
+pub struct ChunkAssembler {
+    writer: Box<dyn tokio::io::AsyncWrite + Send + Unpin>,
+    multipart_id: MultipartId,
+    pending: BTreeMap<u64, Bytes>,
+    next_index: u64,
+    total_chunks: u64,
+    buffer: BytesMut,
+}
+
+impl ChunkAssembler {
+    pub async fn new(
+        store: &dyn ObjectStore,
+        path: &ObjectPath,
+        total_chunks: u64,
+    ) -> Result<Self, Error> {
+        let (multipart_id, writer) = store
+            .put_multipart(path)
+            .await
+            .map_err(Error::ObjectStoreError)?;
+        Ok(ChunkAssembler {
+            writer,
+            multipart_id,
+            pending: BTreeMap::new(),
+            next_index: 0,
+            total_chunks,
+            buffer: BytesMut::new(),
+        })
+    }
+
+    pub fn multipart_id(&self) -> &MultipartId {
+        &self.multipart_id
+    }
+
+    /// Feed a verified chunk in at `index`. Flushes a part to the store as
+    /// soon as the buffered, contiguous-from-`next_index` bytes reach
+    /// `PART_BUFFER_BYTES`, or once the final chunk closes out the file.
+    pub async fn submit(&mut self, index: u64, data: Bytes) -> Result<(), Error> {
+        self.pending.insert(index, data);
+
+        while let Some(data) = self.pending.remove(&self.next_index) {
+            self.buffer.extend_from_slice(&data);
+            self.next_index += 1;
+
+            let is_last_chunk = self.next_index == self.total_chunks;
+            if self.buffer.len() >= PART_BUFFER_BYTES || is_last_chunk {
+                self.flush_part().await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn flush_part(&mut self) -> Result<(), Error> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let part = self.buffer.split().freeze();
+        self.writer
+            .write_all(&part)
+            .await
+            .map_err(|e| Error::SubfileError(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Complete the upload. Must be called once every chunk index has been
+    /// submitted; flushes any buffered remainder before finalizing the
+    /// multipart upload.
+    pub async fn shutdown(mut self) -> Result<(), Error> {
+        self.flush_part().await?;
+        self.writer
+            .shutdown()
+            .await
+            .map_err(|e| Error::SubfileError(e.to_string()))?;
+        Ok(())
+    }
+}
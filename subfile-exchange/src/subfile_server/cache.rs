@@ -0,0 +1,50 @@
+use bytes::Bytes;
+use lru::LruCache;
+use std::num::NonZeroUsize;
+use std::sync::Mutex;
+
+/// Identifies a served byte range uniquely enough to cache it: which
+/// subfile, which file within it, and which window of bytes.
+pub type ChunkCacheKey = (String, String, u64, u64);
+
+/// Bounded in-memory cache of recently served (and already hash-verified)
+/// chunk ranges, so repeated requests for popular ranges don't have to hit
+/// disk or a remote object store again.
+pub struct ChunkCache {
+    inner: Mutex<LruCache<ChunkCacheKey, Bytes>>,
+}
+
+impl ChunkCache {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::new(1).unwrap());
+        ChunkCache {
+            inner: Mutex::new(LruCache::new(capacity)),
+        }
+    }
+
+    pub fn get(&self, key: &ChunkCacheKey) -> Option<Bytes> {
+        self.inner
+            .lock()
+            .expect("chunk cache mutex poisoned")
+            .get(key)
+            .cloned()
+    }
+
+    pub fn insert(&self, key: ChunkCacheKey, value: Bytes) {
+        self.inner
+            .lock()
+            .expect("chunk cache mutex poisoned")
+            .put(key, value);
+    }
+}
+
+impl std::fmt::Debug for ChunkCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let len = self
+            .inner
+            .lock()
+            .map(|cache| cache.len())
+            .unwrap_or_default();
+        f.debug_struct("ChunkCache").field("len", &len).finish()
+    }
+}
@@ -0,0 +1,140 @@
+use async_trait::async_trait;
+use bytes::Bytes;
+use object_store::path::Path as ObjectPath;
+use object_store::ObjectStore;
+use reqwest::Url;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::file_reader::read_chunk;
+
+/// Abstracts byte access to a subfile's underlying files so `file_service`
+/// doesn't need to assume everything lives on local disk.
+#[async_trait]
+pub trait ChunkStore {
+    /// Read the inclusive byte range `(start, end)` of `object`
+    async fn read_range(&self, object: &str, range: (u64, u64)) -> Result<Bytes, anyhow::Error>;
+
+    /// Read the entirety of `object`
+    async fn read_all(&self, object: &str) -> Result<Bytes, anyhow::Error>;
+
+    /// Size of `object` in bytes, without reading its contents. Used by
+    /// startup validation to confirm a served file's actual size matches
+    /// what the manifest expects.
+    async fn size(&self, object: &str) -> Result<u64, anyhow::Error>;
+}
+
+/// Serves subfiles from a directory on local disk, keyed by file name
+/// relative to `root`. This is the storage backend `file_service` always
+/// used before `ChunkStore` was introduced.
+pub struct LocalChunkStore {
+    pub root: PathBuf,
+}
+
+impl LocalChunkStore {
+    pub fn new(root: PathBuf) -> Self {
+        LocalChunkStore { root }
+    }
+
+    fn object_path(&self, object: &str) -> PathBuf {
+        let mut path = self.root.clone();
+        path.push(object);
+        path
+    }
+}
+
+#[async_trait]
+impl ChunkStore for LocalChunkStore {
+    async fn read_range(&self, object: &str, range: (u64, u64)) -> Result<Bytes, anyhow::Error> {
+        let path = self.object_path(object);
+        let data = read_chunk(&path, range)?;
+        Ok(Bytes::from(data))
+    }
+
+    async fn read_all(&self, object: &str) -> Result<Bytes, anyhow::Error> {
+        let path = self.object_path(object);
+        let data = tokio::fs::read(&path).await?;
+        Ok(Bytes::from(data))
+    }
+
+    async fn size(&self, object: &str) -> Result<u64, anyhow::Error> {
+        let path = self.object_path(object);
+        let metadata = tokio::fs::metadata(&path).await?;
+        Ok(metadata.len())
+    }
+}
+
+/// Serves subfiles from an `object_store`-backed bucket (S3, GCS, or any
+/// other scheme `object_store::parse_url` understands), resolving `base_url`
+/// once at construction time rather than re-parsing it (and rebuilding an
+/// HTTP client that doesn't understand `s3://`/`gs://` schemes) on every
+/// read.
+pub struct ObjectStoreChunkStore {
+    store: Arc<dyn ObjectStore>,
+    prefix: ObjectPath,
+}
+
+impl ObjectStoreChunkStore {
+    pub fn new(base_url: &str) -> Result<Self, anyhow::Error> {
+        let url = Url::parse(base_url)
+            .map_err(|e| anyhow::anyhow!("Invalid object store URL {}: {}", base_url, e))?;
+        let (store, prefix) = object_store::parse_url(&url)
+            .map_err(|e| anyhow::anyhow!("Failed to resolve object store URL {}: {}", base_url, e))?;
+        Ok(ObjectStoreChunkStore {
+            store: Arc::from(store),
+            prefix,
+        })
+    }
+
+    fn object_path(&self, object: &str) -> ObjectPath {
+        self.prefix
+            .parts()
+            .chain(ObjectPath::from(object).parts())
+            .collect()
+    }
+}
+
+#[async_trait]
+impl ChunkStore for ObjectStoreChunkStore {
+    async fn read_range(&self, object: &str, range: (u64, u64)) -> Result<Bytes, anyhow::Error> {
+        let (start, end) = range;
+        let path = self.object_path(object);
+        let result = self
+            .store
+            .get_range(&path, start as usize..(end as usize + 1))
+            .await?;
+        Ok(result)
+    }
+
+    async fn read_all(&self, object: &str) -> Result<Bytes, anyhow::Error> {
+        let path = self.object_path(object);
+        let result = self.store.get(&path).await?.bytes().await?;
+        Ok(result)
+    }
+
+    async fn size(&self, object: &str) -> Result<u64, anyhow::Error> {
+        let path = self.object_path(object);
+        let meta = self.store.head(&path).await?;
+        Ok(meta.size as u64)
+    }
+}
+
+/// Pick the appropriate `ChunkStore` implementation for a subfile's
+/// `local_path`: a `gs://`/`s3://`/`http(s)://` prefix is served through
+/// `object_store` (which understands those schemes, unlike a raw HTTP
+/// client), anything else from local disk.
+pub fn chunk_store_for_path(
+    local_path: &Path,
+) -> Result<Box<dyn ChunkStore + Send + Sync>, anyhow::Error> {
+    match local_path.to_str() {
+        Some(path)
+            if path.starts_with("s3://")
+                || path.starts_with("gs://")
+                || path.starts_with("http://")
+                || path.starts_with("https://") =>
+        {
+            Ok(Box::new(ObjectStoreChunkStore::new(path)?))
+        }
+        _ => Ok(Box::new(LocalChunkStore::new(local_path.to_path_buf()))),
+    }
+}
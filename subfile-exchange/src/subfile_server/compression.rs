@@ -0,0 +1,72 @@
+use bytes::Bytes;
+use flate2::write::{DeflateEncoder, GzEncoder};
+use flate2::Compression;
+use hyper::{Body, Request};
+use std::io::Write;
+
+/// Encodings we can transparently apply to a response body
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentEncoding {
+    Gzip,
+    Deflate,
+}
+
+impl ContentEncoding {
+    pub fn as_header_value(&self) -> &'static str {
+        match self {
+            ContentEncoding::Gzip => "gzip",
+            ContentEncoding::Deflate => "deflate",
+        }
+    }
+}
+
+/// Negotiate a response encoding from the client's `Accept-Encoding`
+/// header, preferring gzip. Returns `None` when the client doesn't
+/// advertise support for either scheme.
+pub fn negotiate_encoding(req: &Request<Body>) -> Option<ContentEncoding> {
+    let header = req
+        .headers()
+        .get(http::header::ACCEPT_ENCODING)?
+        .to_str()
+        .ok()?;
+
+    if header.split(',').any(|enc| enc.trim().starts_with("gzip")) {
+        Some(ContentEncoding::Gzip)
+    } else if header
+        .split(',')
+        .any(|enc| enc.trim().starts_with("deflate"))
+    {
+        Some(ContentEncoding::Deflate)
+    } else {
+        None
+    }
+}
+
+/// Skip compression for payloads that are already compressed; recompressing
+/// them wastes CPU for no bandwidth gain.
+pub fn already_compressed(object: &str) -> bool {
+    const COMPRESSED_EXTENSIONS: &[&str] = &[
+        ".gz", ".zip", ".bz2", ".xz", ".zst", ".png", ".jpg", ".jpeg",
+    ];
+    COMPRESSED_EXTENSIONS
+        .iter()
+        .any(|ext| object.ends_with(ext))
+}
+
+/// Encode `data` with the negotiated scheme. Chunk integrity is verified
+/// against the raw bytes before this is called, so compression here is
+/// purely a transport optimization.
+pub fn encode(data: &Bytes, encoding: ContentEncoding) -> Result<Bytes, std::io::Error> {
+    match encoding {
+        ContentEncoding::Gzip => {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(data)?;
+            Ok(Bytes::from(encoder.finish()?))
+        }
+        ContentEncoding::Deflate => {
+            let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(data)?;
+            Ok(Bytes::from(encoder.finish()?))
+        }
+    }
+}
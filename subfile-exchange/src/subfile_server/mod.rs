@@ -1,35 +1,180 @@
-// #![cfg(feature = "acceptor")]
 use anyhow::anyhow;
-use http::header::CONTENT_RANGE;
+use http::header::{CONTENT_ENCODING, CONTENT_RANGE, RANGE};
 use hyper::service::{make_service_fn, service_fn};
+use hyper::server::conn::AddrIncoming;
+use metrics_exporter_prometheus::PrometheusHandle;
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
 use crate::config::{validate_subfile_entries, ServerArgs};
-use crate::file_hasher::verify_chunk;
-use crate::file_reader::read_chunk;
+use crate::discovery::{NoopDiscovery, PeerDiscovery};
 use crate::ipfs::IpfsClient;
 use crate::subfile_reader::read_subfile;
+use crate::subfile_server::auth::{AuthDecision, Authorizer, BearerTokenAuthorizer};
+use crate::subfile_server::cache::ChunkCache;
+use crate::subfile_server::chunk_store::{chunk_store_for_path, ChunkStore};
+use crate::subfile_server::compression::{already_compressed, encode, negotiate_encoding};
+use crate::subfile_server::observability::{
+    init_otlp_tracing, init_prometheus_recorder, metric_names,
+};
+use crate::subfile_server::tls::tls_acceptor;
 use crate::subfile_server::util::{package_version, public_key};
-use crate::types::{Health, Operator, Subfile};
-// #![cfg(feature = "acceptor")]
-// use hyper_rustls::TlsAcceptor;
+use crate::subfile_server::verification::{SubfileHealth, VerificationTracker};
+use crate::types::{ChunkFileMeta, Health, Operator, Subfile};
 use hyper::{Body, Request, Response, StatusCode};
 
-use self::range::{parse_range_header, serve_file, serve_file_range};
+use self::range::parse_ranges_header;
 use self::util::PackageVersion;
 
+pub mod auth;
+pub mod cache;
+pub mod chunk_store;
+pub mod compression;
+pub mod observability;
 pub mod range;
+pub mod sftp;
+pub mod tls;
 pub mod util;
+pub mod verification;
+
+/// Read a byte range (or the whole object, when `range` is `None`) from
+/// `store` and wrap it in an HTTP response, mirroring the
+/// `serve_file`/`serve_file_range` response shape regardless of whether the
+/// bytes came from local disk or an object store. Transparently compresses
+/// the body when the client advertises support for it via `req`. `total_size`
+/// is the full size of `object`, independent of how many bytes this request
+/// happens to serve, since that's what `Content-Range`'s `total` denotes
+/// (RFC 7233 §4.2).
+async fn serve_range_from_store(
+    store: &(dyn ChunkStore + Send + Sync),
+    object: &str,
+    range: Option<(u64, u64)>,
+    total_size: u64,
+    req: &Request<Body>,
+    cache: &ChunkCache,
+    subfile_id: &str,
+) -> Result<Response<Body>, anyhow::Error> {
+    let encoding = if already_compressed(object) {
+        None
+    } else {
+        negotiate_encoding(req)
+    };
+
+    match range {
+        Some((start, end)) => {
+            let cache_key = (subfile_id.to_string(), object.to_string(), start, end);
+            let data = match cache.get(&cache_key) {
+                Some(cached) => {
+                    tracing::debug!(subfile_id, object, start, end, "Chunk cache hit");
+                    cached
+                }
+                None => {
+                    let data = store.read_range(object, (start, end)).await?;
+                    cache.insert(cache_key, data.clone());
+                    data
+                }
+            };
+            metrics::counter!(metric_names::BYTES_SERVED_TOTAL, data.len() as u64);
+            let content_range = range::content_range_header((start, end), total_size);
+            let mut builder = Response::builder()
+                .status(StatusCode::PARTIAL_CONTENT)
+                .header(CONTENT_RANGE, content_range);
+
+            let body = match encoding {
+                Some(encoding) => {
+                    builder = builder.header(CONTENT_ENCODING, encoding.as_header_value());
+                    encode(&data, encoding)?
+                }
+                None => data,
+            };
+            Ok(builder.body(Body::from(body))?)
+        }
+        None => {
+            let data = store.read_all(object).await?;
+            metrics::counter!(metric_names::BYTES_SERVED_TOTAL, data.len() as u64);
+            let mut builder = Response::builder().status(StatusCode::OK);
+
+            let body = match encoding {
+                Some(encoding) => {
+                    builder = builder.header(CONTENT_ENCODING, encoding.as_header_value());
+                    encode(&data, encoding)?
+                }
+                None => data,
+            };
+            Ok(builder.body(Body::from(body))?)
+        }
+    }
+}
+
+/// Read several byte ranges from `store` and wrap them in a single
+/// `multipart/byteranges` response (RFC 7233 §4.1), so a client that has
+/// several contiguous missing chunk indices can fetch them in one request
+/// instead of one HTTP round trip per index. Each range is still served
+/// through `cache` individually, same as the single-range path. `total_size`
+/// is the full size of `object`, used as every part's `Content-Range` total
+/// rather than the sum of bytes this request happens to serve.
+async fn serve_multi_range_from_store(
+    store: &(dyn ChunkStore + Send + Sync),
+    object: &str,
+    ranges: &[(u64, u64)],
+    total_size: u64,
+    cache: &ChunkCache,
+    subfile_id: &str,
+) -> Result<Response<Body>, anyhow::Error> {
+    let mut parts = Vec::with_capacity(ranges.len());
+    for &(start, end) in ranges {
+        let cache_key = (subfile_id.to_string(), object.to_string(), start, end);
+        let data = match cache.get(&cache_key) {
+            Some(cached) => cached,
+            None => {
+                let data = store.read_range(object, (start, end)).await?;
+                cache.insert(cache_key, data.clone());
+                data
+            }
+        };
+        metrics::counter!(metric_names::BYTES_SERVED_TOTAL, data.len() as u64);
+        parts.push(((start, end), data));
+    }
+
+    let body = range::encode_multipart_byteranges(&parts, total_size);
+    Ok(Response::builder()
+        .status(StatusCode::PARTIAL_CONTENT)
+        .header(http::header::CONTENT_TYPE, range::multipart_content_type())
+        .body(Body::from(body))?)
+}
 
 // Define a struct for the server state
-#[derive(Debug)]
 pub struct ServerState {
     pub operator_public_key: String,
     pub subfiles: HashMap<String, Subfile>, // Keyed by IPFS hash
     pub release: PackageVersion,
-    pub free_query_auth_token: Option<String>, // Add bearer prefix
+    // `Arc`, not `Box`: request handlers clone these out of the server-wide
+    // lock and use them after dropping the guard, so the lock only ever
+    // covers the map lookups below, never the I/O that follows.
+    pub authorizer: Arc<dyn Authorizer + Send + Sync>,
+    pub chunk_cache: Arc<ChunkCache>,
+    pub verification: Arc<VerificationTracker>,
+    // Each served subfile's `ChunkStore`, built once at startup from its
+    // `local_path` and reused for every request and background verification
+    // pass against it, rather than re-resolving the URL (and, for an
+    // object-store backend, rebuilding its client) on every read.
+    pub chunk_stores: HashMap<String, Arc<dyn ChunkStore + Send + Sync>>,
+    pub prometheus_handle: Option<PrometheusHandle>,
+    // Announces each accepted subfile so peers can discover this node as a
+    // source for it. Defaults to `NoopDiscovery`, a no-op until a real
+    // DHT-backed `PeerDiscovery` is wired in.
+    pub discovery: Arc<dyn PeerDiscovery + Send + Sync>,
+}
+
+impl std::fmt::Debug for ServerState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ServerState")
+            .field("operator_public_key", &self.operator_public_key)
+            .field("subfiles", &self.subfiles)
+            .field("release", &self.release)
+            .finish()
+    }
 }
 
 pub type ServerContext = Arc<Mutex<ServerState>>;
@@ -40,35 +185,66 @@ pub async fn init_server(client: &IpfsClient, config: ServerArgs) {
         .parse()
         .expect("Invalid address");
 
+    let tls_cert_path = config.tls_cert_path.clone();
+    let tls_key_path = config.tls_key_path.clone();
+    let background_verification_interval = config.background_verification_interval_secs;
+    let sftp_addr = config.sftp_addr.clone();
+
+    if let Err(e) = init_otlp_tracing(&config.otlp_endpoint) {
+        tracing::warn!(err = %e, "Failed to initialize OTLP exporter, continuing with local logs only");
+    }
+
     let state = initialize_subfile_server_context(client, config)
         .await
         .expect("Failed to initiate subfile server");
 
+    if let Some(interval_secs) = background_verification_interval {
+        let state = state.clone();
+        tokio::spawn(verification::spawn_background_verifier(
+            state,
+            std::time::Duration::from_secs(interval_secs),
+        ));
+    }
+
+    // Optional SFTP-style filesystem gateway over the same served subfiles,
+    // enabled by passing --sftp-addr (or the equivalent config field).
+    if let Some(sftp_addr) = &sftp_addr {
+        let addr = sftp_addr
+            .parse()
+            .expect("Invalid --sftp-addr, expected host:port");
+        let sftp_state = state.clone();
+        tokio::spawn(async move {
+            if let Err(e) = sftp::serve(sftp_state, addr).await {
+                tracing::error!(err = %e, "SFTP gateway exited with an error");
+            }
+        });
+    }
+
     // Create hyper server routes
     let make_svc = make_service_fn(|_| {
         let state = state.clone();
         async { Ok::<_, hyper::Error>(service_fn(move |req| handle_request(req, state.clone()))) }
     });
 
-    // TODO: add these to configs
-    // let certs = load_certs("path/to/cert.pem").expect("Failed to load certs");
-    // let key = load_private_key("path/to/key.pem").expect("Failed to load private key");
-
-    // let tls_cfg = {
-    //     let mut cfg = rustls::ServerConfig::new(rustls::NoClientAuth::new());
-    //     cfg.set_single_cert(certs, key).expect("Invalid key or certificate");
-    //     Arc::new(cfg)
-    // };
-
-    // let acceptor = TlsAcceptor::from(tls_cfg);
-    // let server = Server::builder(hyper::server::accept::from_stream(acceptor.accept_stream()))
-    //     .serve(make_svc);
-    let server = hyper::server::Server::bind(&addr).serve(make_svc);
-
-    tracing::info!("Server listening on https://{}", addr);
+    let incoming = AddrIncoming::bind(&addr).expect("Failed to bind address");
+    let acceptor = tls_acceptor(&tls_cert_path, &tls_key_path, incoming)
+        .expect("Failed to build TLS acceptor from configured cert/key");
 
-    if let Err(e) = server.await {
-        tracing::error!("server error: {}", e);
+    match acceptor {
+        Some(acceptor) => {
+            tracing::info!("Server listening on https://{}", addr);
+            let server = hyper::server::Server::builder(acceptor).serve(make_svc);
+            if let Err(e) = server.await {
+                tracing::error!("server error: {}", e);
+            }
+        }
+        None => {
+            tracing::info!("Server listening on http://{} (no TLS configured)", addr);
+            let server = hyper::server::Server::bind(&addr).serve(make_svc);
+            if let Err(e) = server.await {
+                tracing::error!("server error: {}", e);
+            }
+        }
     }
 }
 
@@ -97,57 +273,65 @@ async fn initialize_subfile_server_context(
     let mut server_state = ServerState {
         subfiles: HashMap::new(),
         release: package_version()?,
-        free_query_auth_token,
+        authorizer: Arc::new(BearerTokenAuthorizer {
+            token: free_query_auth_token,
+        }),
         operator_public_key: public_key(&config.mnemonic)
             .expect("Failed to initiate with operator wallet"),
+        chunk_cache: Arc::new(ChunkCache::new(config.chunk_cache_capacity)),
+        verification: Arc::new(VerificationTracker::default()),
+        chunk_stores: HashMap::new(),
+        prometheus_handle: init_prometheus_recorder()
+            .map_err(|e| tracing::warn!(err = %e, "Failed to install Prometheus recorder"))
+            .ok(),
+        discovery: Arc::new(NoopDiscovery),
     };
 
-    // Fetch the file using IPFS client
+    // Fetch the file using IPFS client. Startup only checks that each
+    // chunk file is present on disk with the expected size; the actual
+    // chunk-hash verification is deferred to the first request that
+    // touches a given range (see `file_service`) or to the optional
+    // background sweep in `verification::spawn_background_verifier`, so a
+    // server with terabytes of subfiles can start accepting traffic in
+    // seconds instead of re-hashing everything up front.
     for (ipfs_hash, local_path) in subfile_entries {
         let subfile = read_subfile(client, &ipfs_hash, local_path).await?;
-        tracing::debug!(
-            subfile = tracing::field::debug(&subfile),
-            "Read and verify subfile"
-        );
+        tracing::debug!(subfile = tracing::field::debug(&subfile), "Read subfile");
 
-        //TODO: Refactor
-        // Read all files in subfile to verify locally. This may cause a long initialization time
+        let store: Arc<dyn ChunkStore + Send + Sync> = Arc::from(chunk_store_for_path(&subfile.local_path)?);
         for chunk_file in &subfile.chunk_files {
-            // read file by chunk_file.file_name
-            let mut file_path = subfile.local_path.clone();
-            file_path.push(chunk_file.file_name.clone());
-            tracing::trace!(file_path = tracing::field::debug(&file_path), "Verify file");
-
-            // loop through chunk file  byte range
-            for i in 0..(chunk_file.total_bytes / chunk_file.chunk_size + 1) {
-                // read range
-                let start = i * chunk_file.chunk_size;
-                let end = u64::min(start + chunk_file.chunk_size, chunk_file.total_bytes) - 1;
-                tracing::trace!(
-                    i,
-                    start_byte = tracing::field::debug(&start),
-                    end_byte = tracing::field::debug(&end),
-                    "Verify chunk index"
-                );
-                let chunk_hash = chunk_file.chunk_hashes[i as usize].clone();
-
-                // read chunk
-                let chunk_data = read_chunk(&file_path, (start, end))?;
-                // verify chunk
-                if !verify_chunk(&chunk_data, &chunk_hash) {
-                    tracing::error!(
-                        file = tracing::field::debug(&file_path),
-                        chunk_index = tracing::field::debug(&i),
-                        chunk_hash = tracing::field::debug(&chunk_hash),
-                        "Cannot locally verify the serving file"
-                    );
-                    panic!("Local verification failed")
-                }
+            let size = store.size(&chunk_file.file_name).await.map_err(|e| {
+                anyhow!(
+                    "Serving file {} under {} is missing or unreadable: {}",
+                    chunk_file.file_name,
+                    subfile.local_path.display(),
+                    e
+                )
+            })?;
+            if size != chunk_file.total_bytes {
+                return Err(anyhow!(
+                    "Serving file {} has size {} but manifest expects {}",
+                    chunk_file.file_name,
+                    size,
+                    chunk_file.total_bytes
+                ));
             }
         }
 
-        tracing::info!("Successfully verified the local serving files");
+        tracing::info!(
+            subfile = subfile.ipfs_hash,
+            "Accepted subfile for lazy verification"
+        );
 
+        server_state
+            .verification
+            .register_subfile(&subfile.ipfs_hash);
+        if let Err(e) = server_state.discovery.announce(&subfile.ipfs_hash).await {
+            tracing::warn!(subfile = subfile.ipfs_hash, err = %e, "Failed to announce subfile to peer discovery");
+        }
+        server_state
+            .chunk_stores
+            .insert(subfile.ipfs_hash.clone(), store);
         server_state
             .subfiles
             .insert(subfile.ipfs_hash.clone(), subfile);
@@ -162,7 +346,12 @@ pub async fn handle_request(
     req: Request<Body>,
     context: ServerContext,
 ) -> Result<Response<Body>, anyhow::Error> {
+    let route = req.uri().path().to_string();
+    let span = tracing::info_span!("handle_request", route = route.as_str());
+    let _entered = span.enter();
     tracing::trace!("Received request");
+    metrics::increment_counter!(metric_names::REQUESTS_TOTAL, "route" => route.clone());
+
     match req.uri().path() {
         "/" => Ok(Response::builder()
             .status(StatusCode::OK)
@@ -170,8 +359,10 @@ pub async fn handle_request(
             .unwrap()),
         "/operator" => operator_info(&context).await,
         "/status" => status(&context).await,
-        "/health" => health().await,
+        "/status/health" => subfile_health(&context).await,
+        "/health" => health(&context).await,
         "/version" => version(&context).await,
+        "/metrics" => metrics_endpoint(&context).await,
         path if path.starts_with("/subfiles/id/") => file_service(path, &req, &context).await,
         _ => Ok(Response::builder()
             .status(StatusCode::NOT_FOUND)
@@ -180,16 +371,47 @@ pub async fn handle_request(
     }
 }
 
-/// Endpoint for server health
-pub async fn health() -> Result<Response<Body>, anyhow::Error> {
-    let health = Health { healthy: true };
+/// Endpoint for server health. Reflects lazy/background verification
+/// results: a subfile whose chunks failed to verify marks the server
+/// unhealthy instead of crashing the process.
+pub async fn health(context: &ServerContext) -> Result<Response<Body>, anyhow::Error> {
+    let healthy = context.lock().await.verification.overall_healthy();
+    let health = Health { healthy };
     let health_json = serde_json::to_string(&health).map_err(|e| anyhow!(e.to_string()))?;
     Ok(Response::builder()
-        .status(StatusCode::OK)
+        .status(if healthy {
+            StatusCode::OK
+        } else {
+            StatusCode::SERVICE_UNAVAILABLE
+        })
         .body(Body::from(health_json))
         .unwrap())
 }
 
+/// Prometheus scrape endpoint. A 404 with a short plain-text message if the recorder failed
+/// to install at startup (e.g. a second recorder already registered in this
+/// process), rather than panicking the request handler.
+pub async fn metrics_endpoint(context: &ServerContext) -> Result<Response<Body>, anyhow::Error> {
+    let rendered = context
+        .lock()
+        .await
+        .prometheus_handle
+        .as_ref()
+        .map(|handle| handle.render());
+
+    match rendered {
+        Some(body) => Ok(Response::builder()
+            .status(StatusCode::OK)
+            .header(http::header::CONTENT_TYPE, "text/plain; version=0.0.4")
+            .body(Body::from(body))
+            .unwrap()),
+        None => Ok(Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body("Prometheus recorder not installed".into())
+            .unwrap()),
+    }
+}
+
 /// Endpoint for package version
 pub async fn version(context: &ServerContext) -> Result<Response<Body>, anyhow::Error> {
     let version = context.lock().await.release.version.clone();
@@ -201,10 +423,11 @@ pub async fn version(context: &ServerContext) -> Result<Response<Body>, anyhow::
 
 /// Endpoint for status availability
 pub async fn status(context: &ServerContext) -> Result<Response<Body>, anyhow::Error> {
-    let subfile_mapping = context.lock().await.subfiles.clone();
+    let context_ref = context.lock().await;
     // TODO: check for local access
 
-    let subfile_ipfses: Vec<String> = subfile_mapping
+    let subfile_ipfses: Vec<String> = context_ref
+        .subfiles
         .keys()
         .map(|i| i.to_owned())
         .collect::<Vec<String>>();
@@ -217,6 +440,28 @@ pub async fn status(context: &ServerContext) -> Result<Response<Body>, anyhow::E
         .unwrap())
 }
 
+/// Endpoint reporting verification health per served subfile
+pub async fn subfile_health(context: &ServerContext) -> Result<Response<Body>, anyhow::Error> {
+    let context_ref = context.lock().await;
+    let health: HashMap<String, String> = context_ref
+        .subfiles
+        .keys()
+        .map(|id| {
+            let status = match context_ref.verification.health_of(id) {
+                SubfileHealth::Unverified => "unverified".to_string(),
+                SubfileHealth::Healthy => "healthy".to_string(),
+                SubfileHealth::Unhealthy(reason) => format!("unhealthy: {}", reason),
+            };
+            (id.clone(), status)
+        })
+        .collect();
+    let json = serde_json::to_string(&health).map_err(|e| anyhow!(e.to_string()))?;
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .body(Body::from(json))
+        .unwrap())
+}
+
 // Define a handler function for the `/info` route
 pub async fn operator_info(context: &ServerContext) -> Result<Response<Body>, anyhow::Error> {
     let public_key = context.lock().await.operator_public_key.clone();
@@ -229,6 +474,20 @@ pub async fn operator_info(context: &ServerContext) -> Result<Response<Body>, an
         .unwrap())
 }
 
+/// Everything `file_service` needs out of `ServerState` for one request,
+/// captured while the lock is held so the guard can be dropped before any
+/// of the I/O (verification reads, `store.size()`, range serving) that
+/// follows — all of it can block on local disk or a remote object store,
+/// and holding the server-wide lock across that would serialize every
+/// client, and the background verifier, for the duration of each read.
+struct FileRequestContext {
+    authorizer: Arc<dyn Authorizer + Send + Sync>,
+    chunk_file_meta: Option<ChunkFileMeta>,
+    chunk_store: Arc<dyn ChunkStore + Send + Sync>,
+    verification: Arc<VerificationTracker>,
+    chunk_cache: Arc<ChunkCache>,
+}
+
 // Serve file requests
 pub async fn file_service(
     path: &str,
@@ -237,71 +496,169 @@ pub async fn file_service(
 ) -> Result<Response<Body>, anyhow::Error> {
     tracing::debug!("Received file range request");
     let id = path.trim_start_matches("/subfiles/id/");
+    let object = req
+        .headers()
+        .get("file_name")
+        .and_then(|h| h.to_str().ok())
+        .map(str::to_string);
 
-    let context_ref = context.lock().await;
-    tracing::debug!(
-        subfiles = tracing::field::debug(&context_ref),
-        id,
-        "Received file range request"
-    );
+    let request_ctx = {
+        let context_ref = context.lock().await;
+        tracing::debug!(
+            subfiles = tracing::field::debug(&*context_ref),
+            id,
+            "Received file range request"
+        );
 
-    // Validate the auth token
-    let auth_token = req
-        .headers()
-        .get(http::header::AUTHORIZATION)
-        .and_then(|t| t.to_str().ok());
+        let requested_subfile = match context_ref.subfiles.get(id) {
+            Some(s) => s,
+            None => {
+                tracing::debug!(id, "Requested subfile is not served locally");
+                return Ok(Response::builder()
+                    .status(StatusCode::NOT_FOUND)
+                    .body("Subfile not found".into())
+                    .unwrap());
+            }
+        };
+
+        let chunk_store = match context_ref.chunk_stores.get(id) {
+            Some(store) => store.clone(),
+            None => {
+                return Ok(Response::builder()
+                    .status(StatusCode::INTERNAL_SERVER_ERROR)
+                    .body("Subfile has no associated chunk store".into())
+                    .unwrap());
+            }
+        };
+
+        FileRequestContext {
+            authorizer: context_ref.authorizer.clone(),
+            chunk_file_meta: object.as_ref().and_then(|object| {
+                requested_subfile
+                    .chunk_files
+                    .iter()
+                    .find(|c| &c.file_name == object)
+                    .cloned()
+            }),
+            chunk_store,
+            verification: context_ref.verification.clone(),
+            chunk_cache: context_ref.chunk_cache.clone(),
+        }
+    };
 
-    let free = context_ref.free_query_auth_token.is_none()
-        || (auth_token.is_some()
-            && context_ref.free_query_auth_token.is_some()
-            && auth_token.unwrap() == context_ref.free_query_auth_token.as_deref().unwrap());
+    // Delegate the authorization decision to the configured policy, rather
+    // than hardcoding a single bearer-token comparison here.
+    match request_ctx.authorizer.authorize(req, id).await {
+        AuthDecision::Allow => {}
+        AuthDecision::Unauthorized => {
+            tracing::warn!("Respond with unauthorized query");
+            metrics::increment_counter!(metric_names::AUTH_REJECTIONS_TOTAL, "reason" => "unauthorized");
+            return Ok(Response::builder()
+                .status(StatusCode::UNAUTHORIZED)
+                .body("Paid service is not implemented, need free query authentication".into())
+                .unwrap());
+        }
+        AuthDecision::Forbidden => {
+            tracing::warn!("Respond with forbidden query");
+            metrics::increment_counter!(metric_names::AUTH_REJECTIONS_TOTAL, "reason" => "forbidden");
+            return Ok(Response::builder()
+                .status(StatusCode::FORBIDDEN)
+                .body("Not entitled to this subfile".into())
+                .unwrap());
+        }
+    }
 
-    if !free {
-        tracing::warn!("Respond with unauthorized query");
+    let Some(object) = object else {
         return Ok(Response::builder()
-            .status(StatusCode::UNAUTHORIZED)
-            .body("Paid service is not implemented, need free query authentication".into())
+            .status(StatusCode::NOT_ACCEPTABLE)
+            .body("Missing required chunk_file_hash header".into())
             .unwrap());
-    }
+    };
 
-    let requested_subfile = match context_ref.subfiles.get(id) {
-        Some(s) => s,
-        None => {
-            tracing::debug!(
-                server_context = tracing::field::debug(&context_ref),
-                id,
-                "Requested subfile is not served locally"
-            );
-            return Ok(Response::builder()
-                .status(StatusCode::NOT_FOUND)
-                .body("Subfile not found".into())
-                .unwrap());
+    let store = request_ctx.chunk_store;
+
+    // Parse the standard Range header, which may carry one window or
+    // several comma-separated ones (`bytes=a-b,c-d`).
+    let ranges = match req.headers().get(RANGE) {
+        Some(r) => {
+            tracing::debug!("Parse range header");
+            Some(
+                parse_ranges_header(r)
+                    .map_err(|e| anyhow!(format!("Failed to parse range header: {}", e)))?,
+            )
         }
+        None => None,
     };
 
-    match req.headers().get("file_name") {
-        Some(hash) if hash.to_str().is_ok() => {
-            let mut file_path = requested_subfile.local_path.clone();
-            file_path.push(hash.to_str().unwrap());
-            // Parse the range header to get the start and end bytes
-            match req.headers().get(CONTENT_RANGE) {
-                Some(r) => {
-                    tracing::debug!("Parse content range header");
-                    let range = parse_range_header(r)
-                        .map_err(|e| anyhow!(format!("Failed to parse range header: {}", e)))?;
-                    //TODO: validate receipt
-                    tracing::info!("Serve file range");
-                    serve_file_range(&file_path, range).await
-                }
-                None => {
-                    tracing::info!("Serve file");
-                    serve_file(&file_path).await
-                }
+    // Locally served chunk files get verified lazily, the first time a
+    // range touching them is requested, instead of all upfront at startup.
+    if let Some(chunk_file_meta) = &request_ctx.chunk_file_meta {
+        let verify_ranges = ranges
+            .clone()
+            .unwrap_or_else(|| vec![(0, chunk_file_meta.total_bytes.saturating_sub(1))]);
+        for verify_range in verify_ranges {
+            if let Err(e) = request_ctx
+                .verification
+                .verify_range(id, store.as_ref(), chunk_file_meta, verify_range)
+                .await
+            {
+                tracing::error!(id, object, err = %e, "Chunk verification failed");
+                metrics::increment_counter!(metric_names::CHUNK_VERIFICATION_FAILURES_TOTAL);
+                return Ok(Response::builder()
+                    .status(StatusCode::BAD_GATEWAY)
+                    .body("Chunk integrity verification failed".into())
+                    .unwrap());
             }
         }
-        _ => Ok(Response::builder()
-            .status(StatusCode::NOT_ACCEPTABLE)
-            .body("Missing required chunk_file_hash header".into())
-            .unwrap()),
     }
+
+    let total_size = store.size(&object).await?;
+
+    //TODO: validate receipt
+    let start = std::time::Instant::now();
+    let result = match ranges.as_deref() {
+        Some([range]) => {
+            tracing::info!("Serve file range");
+            serve_range_from_store(
+                store.as_ref(),
+                &object,
+                Some(*range),
+                total_size,
+                req,
+                &request_ctx.chunk_cache,
+                id,
+            )
+            .await
+        }
+        Some(ranges) => {
+            tracing::info!(count = ranges.len(), "Serve multiple file ranges");
+            serve_multi_range_from_store(
+                store.as_ref(),
+                &object,
+                ranges,
+                total_size,
+                &request_ctx.chunk_cache,
+                id,
+            )
+            .await
+        }
+        None => {
+            tracing::info!("Serve file");
+            serve_range_from_store(
+                store.as_ref(),
+                &object,
+                None,
+                total_size,
+                req,
+                &request_ctx.chunk_cache,
+                id,
+            )
+            .await
+        }
+    };
+    metrics::histogram!(
+        metric_names::RANGE_REQUEST_LATENCY_SECONDS,
+        start.elapsed().as_secs_f64()
+    );
+    result
 }
\ No newline at end of file
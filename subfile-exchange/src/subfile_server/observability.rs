@@ -0,0 +1,65 @@
+use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
+use opentelemetry::sdk::trace as sdktrace;
+use opentelemetry_otlp::WithExportConfig;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+
+/// Counter/histogram names emitted for operators to scrape via `/metrics`.
+/// Kept as constants so call sites and dashboards agree on spelling.
+pub mod metric_names {
+    pub const REQUESTS_TOTAL: &str = "subfile_server_requests_total";
+    pub const BYTES_SERVED_TOTAL: &str = "subfile_server_bytes_served_total";
+    pub const CHUNK_VERIFICATION_FAILURES_TOTAL: &str =
+        "subfile_server_chunk_verification_failures_total";
+    pub const AUTH_REJECTIONS_TOTAL: &str = "subfile_server_auth_rejections_total";
+    pub const RANGE_REQUEST_LATENCY_SECONDS: &str = "subfile_server_range_request_latency_seconds";
+}
+
+/// Install the Prometheus metrics recorder and return a handle whose
+/// `render()` backs the `/metrics` endpoint.
+pub fn init_prometheus_recorder() -> Result<PrometheusHandle, anyhow::Error> {
+    let handle = PrometheusBuilder::new().install_recorder()?;
+    Ok(handle)
+}
+
+/// Install the process-wide `tracing` subscriber: a formatting layer for
+/// local logs (honoring `RUST_LOG`, defaulting to `info`), plus an OTLP
+/// span-exporting layer so `handle_request`'s per-route spans (and
+/// everything nested under them) are also shipped to a collector when
+/// `otlp_endpoint` is `Some`. Both layers are composed onto a single
+/// `Registry` and installed with one `try_init()`, since this is the only
+/// place the process installs a subscriber; a second, competing
+/// `try_init()` for the OTLP layer alone would either fail outright or
+/// silently shadow the formatting layer, depending on call order.
+pub fn init_otlp_tracing(otlp_endpoint: &Option<String>) -> Result<(), anyhow::Error> {
+    let env_filter = tracing_subscriber::EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info"));
+
+    let otel_layer = match otlp_endpoint {
+        Some(endpoint) => {
+            let tracer = opentelemetry_otlp::new_pipeline()
+                .tracing()
+                .with_exporter(
+                    opentelemetry_otlp::new_exporter()
+                        .tonic()
+                        .with_endpoint(endpoint),
+                )
+                .with_trace_config(
+                    sdktrace::config().with_resource(opentelemetry::sdk::Resource::new(vec![
+                        opentelemetry::KeyValue::new("service.name", "subfile-server"),
+                    ])),
+                )
+                .install_batch(opentelemetry::runtime::Tokio)?;
+            Some(tracing_opentelemetry::layer().with_tracer(tracer))
+        }
+        None => None,
+    };
+
+    tracing_subscriber::registry()
+        .with(env_filter)
+        .with(tracing_subscriber::fmt::layer())
+        .with(otel_layer)
+        .try_init()?;
+
+    Ok(())
+}
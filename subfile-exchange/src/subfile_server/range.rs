@@ -0,0 +1,220 @@
+use anyhow::anyhow;
+use bytes::Bytes;
+use http::HeaderValue;
+
+/// An inclusive byte range, `start..=end`.
+pub type ByteRange = (u64, u64);
+
+/// Parse a `Range: bytes=a-b,c-d,...` header value into its inclusive byte
+/// ranges, preserving request order so contiguous chunk indices can be
+/// served back together in one `multipart/byteranges` response.
+pub fn parse_ranges_header(value: &HeaderValue) -> Result<Vec<ByteRange>, anyhow::Error> {
+    let value = value
+        .to_str()
+        .map_err(|e| anyhow!("Invalid range header: {}", e))?;
+    let spec = value.strip_prefix("bytes=").ok_or_else(|| {
+        anyhow!(
+            "Unsupported range unit in '{}', only 'bytes' is supported",
+            value
+        )
+    })?;
+
+    spec.split(',')
+        .map(|part| {
+            let part = part.trim();
+            let (start, end) = part
+                .split_once('-')
+                .ok_or_else(|| anyhow!("Malformed range '{}'", part))?;
+            let start: u64 = start
+                .parse()
+                .map_err(|_| anyhow!("Malformed range start in '{}'", part))?;
+            let end: u64 = end
+                .parse()
+                .map_err(|_| anyhow!("Malformed range end in '{}'", part))?;
+            if end < start {
+                return Err(anyhow!("Range end before start in '{}'", part));
+            }
+            Ok((start, end))
+        })
+        .collect()
+}
+
+/// Parse a `Range` header expected to carry a single window, rejecting a
+/// multi-range request. Most local callers only ever deal with one window.
+pub fn parse_range_header(value: &HeaderValue) -> Result<ByteRange, anyhow::Error> {
+    let ranges = parse_ranges_header(value)?;
+    match ranges.as_slice() {
+        [range] => Ok(*range),
+        _ => Err(anyhow!(
+            "Expected exactly one byte range, got {}",
+            ranges.len()
+        )),
+    }
+}
+
+/// Format one or more inclusive byte ranges into a `Range: bytes=...`
+/// request header value.
+pub fn format_ranges_header(ranges: &[ByteRange]) -> String {
+    let parts: Vec<String> = ranges
+        .iter()
+        .map(|(start, end)| format!("{}-{}", start, end))
+        .collect();
+    format!("bytes={}", parts.join(","))
+}
+
+/// Format a `Content-Range: bytes start-end/total` response header value.
+pub fn content_range_header(range: ByteRange, total: u64) -> String {
+    format!("bytes {}-{}/{}", range.0, range.1, total)
+}
+
+/// Parse a `Content-Range: bytes start-end/total` response header.
+pub fn parse_content_range(value: &HeaderValue) -> Result<ByteRange, anyhow::Error> {
+    let value = value
+        .to_str()
+        .map_err(|e| anyhow!("Invalid content-range header: {}", e))?;
+    let spec = value
+        .strip_prefix("bytes ")
+        .ok_or_else(|| anyhow!("Unsupported content-range unit in '{}'", value))?;
+    let (range_part, _total) = spec
+        .split_once('/')
+        .ok_or_else(|| anyhow!("Malformed content-range '{}'", spec))?;
+    let (start, end) = range_part
+        .split_once('-')
+        .ok_or_else(|| anyhow!("Malformed content-range '{}'", spec))?;
+    let start: u64 = start
+        .parse()
+        .map_err(|_| anyhow!("Malformed content-range start in '{}'", spec))?;
+    let end: u64 = end
+        .parse()
+        .map_err(|_| anyhow!("Malformed content-range end in '{}'", spec))?;
+    Ok((start, end))
+}
+
+/// Confirm a response's `Content-Range` matches the range that was actually
+/// requested, so a server that silently ignores `Range` and returns the
+/// whole object (e.g. 200 with no `Content-Range`) isn't mistaken for one
+/// that honored a partial request.
+pub fn validate_content_range(
+    value: &HeaderValue,
+    expected: ByteRange,
+) -> Result<(), anyhow::Error> {
+    let got = parse_content_range(value)?;
+    if got != expected {
+        return Err(anyhow!(
+            "Server returned range {}-{} but {}-{} was requested",
+            got.0,
+            got.1,
+            expected.0,
+            expected.1
+        ));
+    }
+    Ok(())
+}
+
+/// Boundary delimiting parts of a `multipart/byteranges` response. Fixed
+/// rather than randomly generated since this server only ever talks to this
+/// codebase's own client.
+pub const MULTIPART_BOUNDARY: &str = "subfile-byterange-boundary";
+
+/// `Content-Type` header value for a multi-range response.
+pub fn multipart_content_type() -> String {
+    format!("multipart/byteranges; boundary={}", MULTIPART_BOUNDARY)
+}
+
+/// Build a `multipart/byteranges` body from each range's bytes, mirroring
+/// RFC 7233 §4.1: one `--boundary` + `Content-Range` part per range, closed
+/// by a trailing `--boundary--`.
+pub fn encode_multipart_byteranges(parts: &[(ByteRange, Bytes)], total: u64) -> Bytes {
+    let mut body = Vec::new();
+    for (range, data) in parts {
+        body.extend_from_slice(format!("--{}\r\n", MULTIPART_BOUNDARY).as_bytes());
+        body.extend_from_slice(
+            format!("Content-Range: {}\r\n\r\n", content_range_header(*range, total)).as_bytes(),
+        );
+        body.extend_from_slice(data);
+        body.extend_from_slice(b"\r\n");
+    }
+    body.extend_from_slice(format!("--{}--\r\n", MULTIPART_BOUNDARY).as_bytes());
+    Bytes::from(body)
+}
+
+/// Split a `multipart/byteranges` response body back into its
+/// `(range, bytes)` parts, in the order they appear.
+pub fn parse_multipart_byteranges(body: &[u8]) -> Result<Vec<(ByteRange, Bytes)>, anyhow::Error> {
+    let boundary = format!("--{}", MULTIPART_BOUNDARY);
+    let boundary_bytes = boundary.as_bytes();
+    let mut parts = Vec::new();
+    let mut pos = 0;
+
+    while let Some(rel) = find_subslice(&body[pos..], boundary_bytes) {
+        let start = pos + rel + boundary_bytes.len();
+        // The closing boundary has a trailing "--"; stop once we hit it.
+        if body[start..].starts_with(b"--") {
+            break;
+        }
+
+        let header_start = start + skip_crlf(&body[start..]);
+        let header_end = find_subslice(&body[header_start..], b"\r\n\r\n")
+            .ok_or_else(|| anyhow!("Malformed multipart part: missing header terminator"))?
+            + header_start;
+        let headers = std::str::from_utf8(&body[header_start..header_end])
+            .map_err(|e| anyhow!("Malformed multipart part headers: {}", e))?;
+        let content_range = headers
+            .lines()
+            .find_map(|line| line.strip_prefix("Content-Range: "))
+            .ok_or_else(|| anyhow!("Multipart part missing Content-Range header"))?;
+        let range = parse_content_range(&HeaderValue::from_str(content_range)?)?;
+
+        let data_start = header_end + 4;
+        let next_boundary = find_subslice(&body[data_start..], boundary_bytes)
+            .ok_or_else(|| anyhow!("Malformed multipart part: missing closing boundary"))?
+            + data_start;
+        // Trim the trailing "\r\n" the encoder placed before the next boundary.
+        let data_end = next_boundary.saturating_sub(2);
+        parts.push((range, Bytes::copy_from_slice(&body[data_start..data_end])));
+
+        pos = next_boundary;
+    }
+
+    Ok(parts)
+}
+
+fn find_subslice(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+fn skip_crlf(data: &[u8]) -> usize {
+    if data.starts_with(b"\r\n") {
+        2
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn multipart_byteranges_round_trip() {
+        let total = 10_000u64;
+        let parts = vec![
+            ((0u64, 999u64), Bytes::from_static(b"first chunk of bytes")),
+            ((2000, 2999), Bytes::from_static(b"second chunk, different content")),
+            ((9000, 9999), Bytes::from_static(b"trailing chunk")),
+        ];
+
+        let body = encode_multipart_byteranges(&parts, total);
+        let decoded = parse_multipart_byteranges(&body).expect("valid multipart body");
+
+        assert_eq!(decoded, parts);
+    }
+
+    #[test]
+    fn content_range_header_uses_total_not_served_len() {
+        // `total` is the full resource size, which can differ from the
+        // number of bytes actually served in this one range.
+        let header = content_range_header((10, 19), 10_000);
+        assert_eq!(header, "bytes 10-19/10000");
+    }
+}
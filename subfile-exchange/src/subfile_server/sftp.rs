@@ -0,0 +1,348 @@
+use std::net::SocketAddr;
+use std::path::{Component, Path};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::subfile_server::auth::AuthDecision;
+use crate::subfile_server::ServerContext;
+
+/// One entry in a `readdir` listing: either a served subfile (keyed by its
+/// IPFS manifest hash) or one of that subfile's constituent files.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DirEntry {
+    pub name: String,
+    pub is_dir: bool,
+    pub size: u64,
+}
+
+/// Filesystem-shaped view over `ServerState.subfiles`, so an SFTP
+/// (or any other filesystem-style) front-end can present served subfiles
+/// as a read-only directory tree. Paths are `/<ipfs_hash>/<file_name>`:
+/// `/` lists known subfiles, `/<ipfs_hash>` lists that subfile's files.
+/// Implementations translate `read` into the same verified
+/// `ChunkStore::read_range` call `file_service` uses, so a chunk read over
+/// SFTP is just as hash-checked as one read over HTTP.
+#[async_trait]
+pub trait Backend {
+    async fn readdir(&self, path: &Path) -> Result<Vec<DirEntry>, anyhow::Error>;
+    async fn stat(&self, path: &Path) -> Result<DirEntry, anyhow::Error>;
+    async fn open(&self, path: &Path) -> Result<(), anyhow::Error>;
+    async fn read(&self, path: &Path, offset: u64, len: u64) -> Result<Bytes, anyhow::Error>;
+
+    /// Gate a whole SSH session on the token presented as its SFTP
+    /// password, reusing the same bearer-token/free-query check
+    /// `file_service` applies per HTTP request.
+    async fn authenticate(&self, token: Option<&str>) -> bool;
+}
+
+/// `Backend` over a live `ServerContext`. `subfile_id` is the path's first
+/// component; an empty second component addresses the subfile's directory
+/// itself, a non-empty one addresses a specific file within it.
+pub struct SubfileSftpBackend {
+    context: ServerContext,
+}
+
+impl SubfileSftpBackend {
+    pub fn new(context: ServerContext) -> Self {
+        SubfileSftpBackend { context }
+    }
+
+    fn split_path(path: &Path) -> Result<(String, Option<String>), anyhow::Error> {
+        // Only normal components count, so the leading `/` of
+        // `/<ipfs_hash>/<file_name>` isn't taken for the subfile id.
+        let mut components = path.components().filter_map(|c| match c {
+            Component::Normal(s) => s.to_str().map(str::to_string),
+            _ => None,
+        });
+        let subfile_id = components
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("Path must start with a subfile's IPFS hash"))?;
+        Ok((subfile_id, components.next()))
+    }
+}
+
+#[async_trait]
+impl Backend for SubfileSftpBackend {
+    async fn readdir(&self, path: &Path) -> Result<Vec<DirEntry>, anyhow::Error> {
+        let state = self.context.lock().await;
+
+        if path.as_os_str().is_empty() || path == Path::new("/") {
+            return Ok(state
+                .subfiles
+                .keys()
+                .map(|subfile_id| DirEntry {
+                    name: subfile_id.clone(),
+                    is_dir: true,
+                    size: 0,
+                })
+                .collect());
+        }
+
+        let (subfile_id, _) = Self::split_path(path)?;
+        let subfile = state
+            .subfiles
+            .get(&subfile_id)
+            .ok_or_else(|| anyhow::anyhow!("Unknown subfile {}", subfile_id))?;
+
+        Ok(subfile
+            .chunk_files
+            .iter()
+            .map(|chunk_file| DirEntry {
+                name: chunk_file.file_name.clone(),
+                is_dir: false,
+                size: chunk_file.total_bytes,
+            })
+            .collect())
+    }
+
+    async fn stat(&self, path: &Path) -> Result<DirEntry, anyhow::Error> {
+        let (subfile_id, file_name) = Self::split_path(path)?;
+        let state = self.context.lock().await;
+        let subfile = state
+            .subfiles
+            .get(&subfile_id)
+            .ok_or_else(|| anyhow::anyhow!("Unknown subfile {}", subfile_id))?;
+
+        match file_name {
+            None => Ok(DirEntry {
+                name: subfile_id,
+                is_dir: true,
+                size: 0,
+            }),
+            Some(file_name) => {
+                let chunk_file = subfile
+                    .chunk_files
+                    .iter()
+                    .find(|c| c.file_name == file_name)
+                    .ok_or_else(|| {
+                        anyhow::anyhow!("Unknown file {} in subfile {}", file_name, subfile_id)
+                    })?;
+                Ok(DirEntry {
+                    name: file_name,
+                    is_dir: false,
+                    size: chunk_file.total_bytes,
+                })
+            }
+        }
+    }
+
+    async fn open(&self, path: &Path) -> Result<(), anyhow::Error> {
+        self.stat(path).await.map(|_| ())
+    }
+
+    async fn read(&self, path: &Path, offset: u64, len: u64) -> Result<Bytes, anyhow::Error> {
+        let (subfile_id, file_name) = Self::split_path(path)?;
+        let file_name = file_name
+            .ok_or_else(|| anyhow::anyhow!("Cannot read a subfile directory: {}", subfile_id))?;
+        let end = offset + len.saturating_sub(1);
+
+        // Snapshot what's needed and drop the lock before the verify/read
+        // I/O below, same as the HTTP path in `file_service`.
+        let (chunk_file_meta, store, verification) = {
+            let state = self.context.lock().await;
+            let subfile = state
+                .subfiles
+                .get(&subfile_id)
+                .ok_or_else(|| anyhow::anyhow!("Unknown subfile {}", subfile_id))?;
+            let chunk_file_meta = subfile
+                .chunk_files
+                .iter()
+                .find(|c| c.file_name == file_name)
+                .ok_or_else(|| {
+                    anyhow::anyhow!("Unknown file {} in subfile {}", file_name, subfile_id)
+                })?
+                .clone();
+            let store = state
+                .chunk_stores
+                .get(&subfile_id)
+                .ok_or_else(|| anyhow::anyhow!("Subfile {} has no associated chunk store", subfile_id))?
+                .clone();
+            (chunk_file_meta, store, state.verification.clone())
+        };
+
+        verification
+            .verify_range(&subfile_id, store.as_ref(), &chunk_file_meta, (offset, end))
+            .await?;
+
+        store.read_range(&file_name, (offset, end)).await
+    }
+
+    async fn authenticate(&self, token: Option<&str>) -> bool {
+        let state = self.context.lock().await;
+        // There's no single subfile to scope this to at the point an SSH
+        // session authenticates, so this only makes sense for an
+        // authorizer that doesn't vary its decision per subfile (e.g.
+        // `BearerTokenAuthorizer`/`AllowAllAuthorizer`); a
+        // `PerSubfileTokenAuthorizer` will reject every session, matching
+        // its "forbidden unless explicitly allowed" default.
+        matches!(
+            state.authorizer.authorize_token(token, "").await,
+            AuthDecision::Allow
+        )
+    }
+}
+
+/// A single request frame of the listener's wire protocol: one line of JSON
+/// per request, read/written by `serve`/`handle_connection`.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum SftpRequest {
+    Readdir { path: String, token: Option<String> },
+    Stat { path: String, token: Option<String> },
+    Read {
+        path: String,
+        offset: u64,
+        len: u64,
+        token: Option<String>,
+    },
+}
+
+/// Response frame mirroring `SftpRequest`, one line of JSON per response.
+/// `data` is base64-encoded so a `Read` response round-trips through the
+/// same line-delimited JSON framing as every other op.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum SftpResponse {
+    Entries(Vec<DirEntry>),
+    Entry(DirEntry),
+    Data { data_base64: String },
+    Err { error: String },
+}
+
+/// Status: not real SFTP/SSH. This is a minimal newline-delimited-JSON RPC
+/// transport over `Backend`, reachable over a raw TCP socket today so
+/// `Backend` has *some* runnable entrypoint — but a client still has to
+/// speak this crate's bespoke `SftpRequest`/`SftpResponse` framing, so it
+/// does not satisfy the original goal of letting existing SFTP-capable
+/// tooling pull subfiles over an authenticated SSH channel without that.
+/// Genuine SFTP/SSH support (subsystem handling over an SSH transport, e.g.
+/// via `russh`/`russh-sftp`-style crates) is tracked as separate follow-up
+/// work requiring a dependency not present anywhere in this checkout; it is
+/// not implemented here and this module should not be read as satisfying
+/// that request on its own. `Backend` is the reusable part: a real SSH/SFTP
+/// server would sit in front of the exact same implementation without this
+/// module needing to change.
+pub async fn serve(context: ServerContext, addr: SocketAddr) -> Result<(), anyhow::Error> {
+    let backend = Arc::new(SubfileSftpBackend::new(context));
+    let listener = TcpListener::bind(addr).await?;
+    tracing::info!(%addr, "bespoke JSON-RPC filesystem gateway listening (not real SFTP/SSH)");
+
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let backend = backend.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(backend, stream).await {
+                tracing::warn!(%peer, err = %e, "filesystem gateway connection ended with an error");
+            }
+        });
+    }
+}
+
+async fn handle_connection(
+    backend: Arc<SubfileSftpBackend>,
+    stream: TcpStream,
+) -> Result<(), anyhow::Error> {
+    let (read_half, mut write_half) = stream.into_split();
+    let mut lines = BufReader::new(read_half).lines();
+
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match serde_json::from_str::<SftpRequest>(&line) {
+            Ok(request) => handle_request(&backend, request).await,
+            Err(e) => SftpResponse::Err {
+                error: format!("Malformed request: {}", e),
+            },
+        };
+
+        let mut out = serde_json::to_string(&response)?;
+        out.push('\n');
+        write_half.write_all(out.as_bytes()).await?;
+    }
+
+    Ok(())
+}
+
+async fn handle_request(backend: &SubfileSftpBackend, request: SftpRequest) -> SftpResponse {
+    match request {
+        SftpRequest::Readdir { path, token } => {
+            if !backend.authenticate(token.as_deref()).await {
+                return SftpResponse::Err {
+                    error: "Unauthorized".to_string(),
+                };
+            }
+            match backend.readdir(Path::new(&path)).await {
+                Ok(entries) => SftpResponse::Entries(entries),
+                Err(e) => SftpResponse::Err {
+                    error: e.to_string(),
+                },
+            }
+        }
+        SftpRequest::Stat { path, token } => {
+            if !backend.authenticate(token.as_deref()).await {
+                return SftpResponse::Err {
+                    error: "Unauthorized".to_string(),
+                };
+            }
+            match backend.stat(Path::new(&path)).await {
+                Ok(entry) => SftpResponse::Entry(entry),
+                Err(e) => SftpResponse::Err {
+                    error: e.to_string(),
+                },
+            }
+        }
+        SftpRequest::Read {
+            path,
+            offset,
+            len,
+            token,
+        } => {
+            if !backend.authenticate(token.as_deref()).await {
+                return SftpResponse::Err {
+                    error: "Unauthorized".to_string(),
+                };
+            }
+            match backend.read(Path::new(&path), offset, len).await {
+                Ok(data) => SftpResponse::Data {
+                    data_base64: base64_encode(&data),
+                },
+                Err(e) => SftpResponse::Err {
+                    error: e.to_string(),
+                },
+            }
+        }
+    }
+}
+
+/// Small dependency-free base64 encoder, since `Read` responses are the only
+/// place this listener needs one.
+fn base64_encode(data: &Bytes) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity((data.len() + 2) / 3 * 4);
+    for chunk in data.chunks(3) {
+        let b0 = chunk[0] as u32;
+        let b1 = *chunk.get(1).unwrap_or(&0) as u32;
+        let b2 = *chunk.get(2).unwrap_or(&0) as u32;
+        let n = (b0 << 16) | (b1 << 8) | b2;
+        out.push(ALPHABET[((n >> 18) & 0x3F) as usize] as char);
+        out.push(ALPHABET[((n >> 12) & 0x3F) as usize] as char);
+        out.push(if chunk.len() > 1 {
+            ALPHABET[((n >> 6) & 0x3F) as usize] as char
+        } else {
+            '='
+        });
+        out.push(if chunk.len() > 2 {
+            ALPHABET[(n & 0x3F) as usize] as char
+        } else {
+            '='
+        });
+    }
+    out
+}
@@ -0,0 +1,63 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use hyper::server::conn::AddrIncoming;
+use hyper_rustls::TlsAcceptor;
+use rustls::{Certificate, PrivateKey};
+
+/// Load a PEM certificate chain from disk
+fn load_certs(path: &Path) -> Result<Vec<Certificate>, anyhow::Error> {
+    let file = File::open(path)?;
+    let mut reader = BufReader::new(file);
+    let certs = rustls_pemfile::certs(&mut reader)?
+        .into_iter()
+        .map(Certificate)
+        .collect();
+    Ok(certs)
+}
+
+/// Load a PEM private key from disk, accepting PKCS8, RSA, or EC encodings
+fn load_private_key(path: &Path) -> Result<PrivateKey, anyhow::Error> {
+    let file = File::open(path)?;
+    let mut reader = BufReader::new(file);
+
+    loop {
+        match rustls_pemfile::read_one(&mut reader)? {
+            Some(rustls_pemfile::Item::PKCS8Key(key)) => return Ok(PrivateKey(key)),
+            Some(rustls_pemfile::Item::RSAKey(key)) => return Ok(PrivateKey(key)),
+            Some(rustls_pemfile::Item::ECKey(key)) => return Ok(PrivateKey(key)),
+            None => {
+                return Err(anyhow::anyhow!(
+                    "No private key found in {}",
+                    path.display()
+                ))
+            }
+            _ => continue,
+        }
+    }
+}
+
+/// Wrap a bound `AddrIncoming` in a `TlsAcceptor` built from a cert/key PEM
+/// pair. Returns `None` when either path is absent so `init_server` can fall
+/// back to serving plaintext.
+pub fn tls_acceptor(
+    tls_cert_path: &Option<String>,
+    tls_key_path: &Option<String>,
+    incoming: AddrIncoming,
+) -> Result<Option<TlsAcceptor>, anyhow::Error> {
+    let (cert_path, key_path) = match (tls_cert_path, tls_key_path) {
+        (Some(cert), Some(key)) => (cert, key),
+        _ => return Ok(None),
+    };
+
+    let certs = load_certs(Path::new(cert_path))?;
+    let key = load_private_key(Path::new(key_path))?;
+
+    let acceptor = TlsAcceptor::builder()
+        .with_single_cert(certs, key)?
+        .with_all_versions_alpn()
+        .with_incoming(incoming);
+
+    Ok(Some(acceptor))
+}
@@ -0,0 +1,200 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::chunking;
+use crate::file_hasher::verify_chunk;
+use crate::merkle;
+use crate::subfile_server::chunk_store::ChunkStore;
+use crate::subfile_server::ServerContext;
+use crate::types::ChunkFileMeta;
+
+/// Health of a single served subfile, as surfaced on `/status`/`/health`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SubfileHealth {
+    /// Still waiting on its first lazy/background verification pass
+    Unverified,
+    Healthy,
+    Unhealthy(String),
+}
+
+/// Tracks, per subfile and chunk file, which chunk indices have already
+/// been hash-verified, plus the overall health derived from that. Startup
+/// only checks file presence/size; verification of chunk bytes happens
+/// lazily the first time a range is requested, or via the background
+/// sweep in `spawn_background_verifier`.
+#[derive(Debug, Default)]
+pub struct VerificationTracker {
+    // Keyed by (subfile_id, file_name) -> a verified bitmap, one bool per chunk index
+    verified: Mutex<HashMap<(String, String), Vec<bool>>>,
+    health: Mutex<HashMap<String, SubfileHealth>>,
+}
+
+impl VerificationTracker {
+    pub fn register_subfile(&self, subfile_id: &str) {
+        self.health
+            .lock()
+            .expect("verification mutex poisoned")
+            .insert(subfile_id.to_string(), SubfileHealth::Unverified);
+    }
+
+    pub fn health_of(&self, subfile_id: &str) -> SubfileHealth {
+        self.health
+            .lock()
+            .expect("verification mutex poisoned")
+            .get(subfile_id)
+            .cloned()
+            .unwrap_or(SubfileHealth::Unverified)
+    }
+
+    pub fn overall_healthy(&self) -> bool {
+        self.health
+            .lock()
+            .expect("verification mutex poisoned")
+            .values()
+            .all(|h| !matches!(h, SubfileHealth::Unhealthy(_)))
+    }
+
+    fn is_verified(&self, subfile_id: &str, file_name: &str, index: u64, total_chunks: u64) -> bool {
+        let mut verified = self.verified.lock().expect("verification mutex poisoned");
+        let bitmap = verified
+            .entry((subfile_id.to_string(), file_name.to_string()))
+            .or_insert_with(|| vec![false; total_chunks as usize]);
+        bitmap.get(index as usize).copied().unwrap_or(false)
+    }
+
+    fn mark_verified(&self, subfile_id: &str, file_name: &str, index: u64) {
+        let mut verified = self.verified.lock().expect("verification mutex poisoned");
+        if let Some(bitmap) = verified.get_mut(&(subfile_id.to_string(), file_name.to_string())) {
+            if let Some(slot) = bitmap.get_mut(index as usize) {
+                *slot = true;
+            }
+        }
+        // A failure already recorded for this subfile stays in place; one
+        // good chunk doesn't clear it.
+        let mut health = self.health.lock().expect("verification mutex poisoned");
+        let entry = health
+            .entry(subfile_id.to_string())
+            .or_insert(SubfileHealth::Unverified);
+        if !matches!(entry, SubfileHealth::Unhealthy(_)) {
+            *entry = SubfileHealth::Healthy;
+        }
+    }
+
+    fn mark_unhealthy(&self, subfile_id: &str, reason: String) {
+        tracing::error!(subfile_id, reason, "Subfile marked unhealthy");
+        self.health
+            .lock()
+            .expect("verification mutex poisoned")
+            .insert(subfile_id.to_string(), SubfileHealth::Unhealthy(reason));
+    }
+
+    /// Verify the chunk indices covered by `range` for `file_name`, reading
+    /// and hashing only the indices not already known-good. Used both by
+    /// the lazy first-request path and the background sweep. `store` is the
+    /// subfile's already-resolved `ChunkStore` (the caller holds the one
+    /// `ServerState` built for it at startup, rather than this re-resolving
+    /// the subfile's storage location on every call), so this verifies
+    /// object-store-backed subfiles the same way it verifies local ones.
+    ///
+    /// Each chunk is checked twice: its bytes must hash to the leaf recorded
+    /// in `chunk_file.chunk_hashes`, and that leaf must fold up to
+    /// `chunk_file.merkle_root` via its Merkle path. The second check catches
+    /// a `chunk_hashes` list tampered with independently of the file bytes,
+    /// since the root is the value actually anchored in the published
+    /// manifest.
+    pub async fn verify_range(
+        &self,
+        subfile_id: &str,
+        store: &(dyn ChunkStore + Send + Sync),
+        chunk_file: &ChunkFileMeta,
+        range: (u64, u64),
+    ) -> Result<(), anyhow::Error> {
+        let file_name = &chunk_file.file_name;
+        let offsets = chunk_file.chunk_offsets.as_deref();
+        let total_chunks = chunking::chunk_count(chunk_file.total_bytes, chunk_file.chunk_size, offsets);
+        let first_index = chunking::chunk_index_at(chunk_file.chunk_size, offsets, range.0);
+        let last_index = chunking::chunk_index_at(chunk_file.chunk_size, offsets, range.1);
+
+        for index in first_index..=last_index {
+            if self.is_verified(subfile_id, file_name, index, total_chunks) {
+                continue;
+            }
+
+            let (start, end) =
+                chunking::chunk_window(chunk_file.total_bytes, chunk_file.chunk_size, offsets, index);
+            let chunk_hash = chunk_file.chunk_hashes[index as usize].clone();
+            let chunk_data = store.read_range(file_name, (start, end)).await?;
+
+            if !verify_chunk(&chunk_data, &chunk_hash) {
+                let reason = format!("Chunk {} of {} failed verification", index, file_name);
+                self.mark_unhealthy(subfile_id, reason.clone());
+                return Err(anyhow::anyhow!(reason));
+            }
+
+            let path = merkle::build_path(&chunk_file.chunk_hashes, index as usize);
+            if !merkle::verify_path(&chunk_hash, &path, &chunk_file.merkle_root) {
+                let reason = format!(
+                    "Chunk {} of {} does not fold up to the subfile's Merkle root",
+                    index, file_name
+                );
+                self.mark_unhealthy(subfile_id, reason.clone());
+                return Err(anyhow::anyhow!(reason));
+            }
+
+            self.mark_verified(subfile_id, file_name, index);
+        }
+
+        Ok(())
+    }
+}
+
+/// Walk every unverified chunk across every served subfile at a throttled
+/// rate, marking subfiles unhealthy instead of crashing the process when a
+/// chunk fails to verify. Intended to run as a detached background task
+/// for servers that opt into it. Only the per-iteration snapshot below is
+/// taken under the server-wide lock; the actual verification I/O runs
+/// against cloned `Arc` handles with the lock released, so this sweep
+/// shares the stores and verification tracker with live traffic instead of
+/// blocking it.
+pub async fn spawn_background_verifier(context: ServerContext, throttle: Duration) {
+    loop {
+        let (subfiles, chunk_stores, tracker) = {
+            let state = context.lock().await;
+            (
+                state.subfiles.clone(),
+                state.chunk_stores.clone(),
+                state.verification.clone(),
+            )
+        };
+
+        if subfiles.is_empty() {
+            tokio::time::sleep(throttle).await;
+            continue;
+        }
+
+        for (subfile_id, subfile) in subfiles {
+            let Some(store) = chunk_stores.get(&subfile_id) else {
+                continue;
+            };
+
+            for chunk_file_meta in &subfile.chunk_files {
+                let total_bytes = chunk_file_meta.total_bytes;
+
+                if let Err(e) = tracker
+                    .verify_range(
+                        &subfile_id,
+                        store.as_ref(),
+                        chunk_file_meta,
+                        (0, total_bytes.saturating_sub(1)),
+                    )
+                    .await
+                {
+                    tracing::warn!(subfile_id, err = %e, "Background verification failed");
+                }
+
+                tokio::time::sleep(throttle).await;
+            }
+        }
+    }
+}